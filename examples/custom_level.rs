@@ -0,0 +1,61 @@
+//! Builds a level and a program in code, without any yaml, and steps through a test case.
+
+use tur::{Card, Direction, Instruction, Level, LevelDto, Program, Target, TestCase, TestCaseExecution};
+
+fn main() {
+    // Light the cell two to the right of the start, and stop there
+    let level = Level {
+        name: "two_right".to_string(),
+        description: "Light the cell two to the right and stop on it".to_string(),
+        cases: vec![TestCase {
+            initial_tape: Default::default(),
+            target: Some(Target::tape(&[2])),
+            ..TestCase::default()
+        }],
+        solution: None,
+        hints: vec![],
+    };
+    let step = |next_card| Instruction {
+        write_symbol: None,
+        move_direction: Some(Direction::Right),
+        next_card,
+        other_tapes: Vec::new(),
+    };
+    let light = Instruction {
+        write_symbol: Some(true),
+        move_direction: None,
+        next_card: None,
+        other_tapes: Vec::new(),
+    };
+    let program = Program {
+        name: "two_right".to_string(),
+        description: String::new(),
+        initial_card: 0,
+        cards: vec![
+            card("First", step(Some(1)), step(Some(1))),
+            card("Second", step(Some(2)), step(Some(2))),
+            card("Light", light.clone(), light),
+        ],
+    };
+
+    let mut tce = TestCaseExecution::new(level.cases[0].clone(), program);
+    while !tce.is_terminated() {
+        let card = tce.get_current_card().map(|card| card.name.clone()).unwrap_or_default();
+        tce.step();
+        println!("{:>8}: head at {}", card, tce.get_current_position());
+    }
+    println!("{:?}", tce.get_state());
+
+    // Levels built in code can be saved like any other level
+    print!("{}", serde_yaml::to_string(&LevelDto::from(level)).unwrap());
+}
+
+fn card(name: &str, tape_on: Instruction, tape_off: Instruction) -> Card {
+    Card {
+        name: name.to_string(),
+        tape_on,
+        tape_off,
+        other_instructions: Vec::new(),
+        alternatives: Vec::new(),
+    }
+}
@@ -0,0 +1,32 @@
+//! Loads a level and a program from yaml files and checks whether the program solves the level.
+//!
+//! cargo run --example run_level -- res/level/increment.yaml res/program/increment_solution.yaml
+
+use std::env;
+use std::path::Path;
+
+use anyhow::Result;
+use tur::loader::{read_level, read_program};
+use tur::{LevelExecution, TestCaseExecutionState};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (level_path, program_path) = match args.as_slice() {
+        [level, program] => (level.as_str(), program.as_str()),
+        _ => ("res/level/increment.yaml", "res/program/increment_solution.yaml"),
+    };
+    let level = read_level(Path::new(level_path))?;
+    let program = read_program(Path::new(program_path))?;
+
+    let mut execution = LevelExecution::new(level, program);
+    execution.run(10_000);
+    for (index, tce) in execution.executions.iter().enumerate() {
+        match tce.get_state() {
+            TestCaseExecutionState::Success => println!("Case {}: solved in {} steps", index + 1, tce.steps),
+            TestCaseExecutionState::Failure { errors } => println!("Case {}: {}", index + 1, errors.join(", ")),
+            _ => println!("Case {}: still running after {} steps", index + 1, tce.steps),
+        }
+    }
+    println!("{} is {}", execution.level.name, if execution.is_solved() { "solved" } else { "not solved" });
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use crate::decider::{Decider, Verdict};
+use crate::program::{Card, Direction, Instruction, Program};
+use crate::synthesis::card_name;
+
+pub struct Champion {
+    pub program: Program,
+    pub steps: u64,
+    pub lit: usize,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub machines: usize,
+    /// Amount of machines decided by each decider
+    pub decided: BTreeMap<&'static str, usize>,
+    pub most_steps: Option<Champion>,
+    pub most_lit: Option<Champion>,
+    pub holdouts: Vec<Program>,
+}
+
+/// Enumerates all programs with at most `cards` cards in tree-normal form and classifies them
+/// with the first of `deciders` that can.
+///
+/// Undefined instructions halt. The enumeration starts from a program with only undefined
+/// instructions, and whenever a program halts on an undefined instruction, all ways of defining
+/// it are enumerated in turn. The very first instruction always moves right, as programs that
+/// start by moving left are mirror images of those that don't.
+pub fn enumerate(cards: usize, deciders: &[Box<dyn Decider>]) -> Report {
+    let mut report = Report::default();
+    let mut slots = vec![[None, None]; cards];
+    visit(&mut slots, 1, deciders, &mut report);
+    report
+}
+
+type Slots = Vec<[Option<Instruction>; 2]>;
+
+fn visit(slots: &mut Slots, used_cards: usize, deciders: &[Box<dyn Decider>], report: &mut Report) {
+    let program = to_program(slots);
+    let Some((name, verdict)) = deciders.iter()
+        .find_map(|decider| decider.decide(&program).map(|verdict| (decider.name(), verdict))) else {
+        report.machines += 1;
+        report.holdouts.push(program);
+        return;
+    };
+    report.machines += 1;
+    *report.decided.entry(name).or_default() += 1;
+
+    if let Verdict::Halts { steps, lit, card, on } = verdict {
+        if report.most_steps.as_ref().is_none_or(|champion| steps > champion.steps) {
+            report.most_steps = Some(Champion { program: program.clone(), steps, lit });
+        }
+        if report.most_lit.as_ref().is_none_or(|champion| lit > champion.lit) {
+            report.most_lit = Some(Champion { program, steps, lit });
+        }
+
+        let undefined = slots.iter().flatten().filter(|slot| slot.is_none()).count();
+        if undefined <= 1 || slots[card][on as usize].is_some() {
+            return;
+        }
+        let first = undefined == 2 * slots.len();
+        let moves = if first { vec![Direction::Right] } else { vec![Direction::Left, Direction::Right] };
+        let next_cards = (used_cards + 1).min(slots.len());
+        for next_card in 0..next_cards {
+            for &move_direction in &moves {
+                for write_symbol in [false, true] {
+                    slots[card][on as usize] = Some(Instruction {
+                        write_symbol: Some(write_symbol),
+                        move_direction: Some(move_direction),
+                        next_card: Some(next_card),
+                        other_tapes: Vec::new(),
+                    });
+                    visit(slots, used_cards.max(next_card + 1), deciders, report);
+                }
+            }
+        }
+        slots[card][on as usize] = None;
+    }
+}
+
+fn to_program(slots: &Slots) -> Program {
+    let halt = Instruction {
+        write_symbol: Some(true),
+        move_direction: Some(Direction::Right),
+        next_card: None,
+        other_tapes: Vec::new(),
+    };
+    let cards = slots.iter()
+        .enumerate()
+        .map(|(index, [off, on])| Card {
+            name: card_name(index),
+            tape_on: on.clone().unwrap_or(halt.clone()),
+            tape_off: off.clone().unwrap_or(halt.clone()),
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
+        })
+        .collect();
+    Program {
+        name: String::new(),
+        description: String::new(),
+        initial_card: 0,
+        cards,
+    }
+}
+
+/// The usual compact notation for busy beaver candidates, e.g. `1RB1LB_1LA1RZ`,
+/// where `Z` is a halting instruction.
+pub fn standard_notation(program: &Program) -> String {
+    let instruction = |instruction: &Instruction, read: bool| {
+        let write = if instruction.write_symbol.unwrap_or(read) { '1' } else { '0' };
+        let direction = match instruction.move_direction {
+            Some(Direction::Left) => 'L',
+            Some(Direction::Right) => 'R',
+            Some(Direction::Up) => 'U',
+            Some(Direction::Down) => 'D',
+            None => 'N',
+        };
+        let next = match instruction.next_card {
+            Some(index) => card_name(index),
+            None => "Z".to_string(),
+        };
+        format!("{}{}{}", write, direction, next)
+    };
+    program.cards.iter()
+        .map(|card| instruction(&card.tape_off, false) + &instruction(&card.tape_on, true))
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::beaver::{enumerate, standard_notation};
+    use crate::decider;
+
+    #[test]
+    fn two_cards() {
+        let report = enumerate(2, &decider::builtins(100));
+        assert!(report.holdouts.is_empty());
+        let most_steps = report.most_steps.unwrap();
+        assert_eq!(most_steps.steps, 6);
+        assert_eq!(report.most_lit.unwrap().lit, 4);
+        assert_eq!(standard_notation(&most_steps.program).len(), 13);
+    }
+
+    #[test]
+    fn three_cards() {
+        let report = enumerate(3, &decider::builtins(100));
+        assert_eq!(report.most_steps.unwrap().steps, 21);
+        assert_eq!(report.most_lit.unwrap().lit, 6);
+    }
+}
@@ -0,0 +1,81 @@
+use std::ops::ControlFlow;
+
+use crate::execution::{ExecutionObserver, Step};
+use crate::program::Program;
+
+/// Stops a run right after the program changes to one of a set of cards, before that card runs.
+/// Cards that run again and again do not stop the run each time, so it can be resumed.
+#[derive(Clone, Default, Debug)]
+pub struct Breakpoints {
+    cards: Vec<usize>,
+    /// The card of the last stop
+    pub hit: Option<usize>,
+}
+
+impl Breakpoints {
+    pub fn new(cards: Vec<usize>) -> Breakpoints {
+        Breakpoints { cards, hit: None }
+    }
+
+    /// Breakpoints on the cards of the program with these names
+    pub fn on_cards(program: &Program, names: &[String]) -> Result<Breakpoints, String> {
+        let cards = names.iter()
+            .map(|name| program.cards.iter().position(|card| &card.name == name)
+                .ok_or_else(|| format!("Card {} not found in program {}", name, program.name)))
+            .collect::<Result<_, _>>()?;
+        Ok(Breakpoints::new(cards))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+}
+
+impl ExecutionObserver for Breakpoints {
+    fn on_step(&mut self, step: &Step) -> ControlFlow<()> {
+        match step.next_card {
+            Some(card) if card != step.card && self.cards.contains(&card) => {
+                self.hit = Some(card);
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::breakpoint::Breakpoints;
+    use crate::execution::LevelExecution;
+    use crate::levels;
+    use crate::program::Program;
+    use crate::programs;
+
+    fn box_solution() -> Program {
+        programs::builtins().into_iter().find(|program| program.name == "box_solution").unwrap()
+    }
+
+    #[test]
+    fn runs_stop_when_a_card_with_a_breakpoint_is_next() {
+        let level = levels::builtins().into_iter().find(|level| level.name == "box").unwrap();
+        let program = box_solution();
+        let mut breakpoints = Breakpoints::on_cards(&program, &["Left".to_string()]).unwrap();
+        let mut execution = LevelExecution::new(level.clone(), program.clone());
+
+        assert!(!execution.run_observed(1000, &mut breakpoints));
+        assert_eq!(breakpoints.hit, Some(2));
+        let steps = execution.get_steps();
+        assert!(steps > 0);
+
+        execution.run_observed(1000, &mut breakpoints);
+        assert!(execution.get_steps() > steps);
+
+        assert!(LevelExecution::new(level, program).run_observed(1000, &mut Breakpoints::default()));
+    }
+
+    #[test]
+    fn cards_that_do_not_exist_are_reported() {
+        let error = Breakpoints::on_cards(&box_solution(), &["Nowhere".to_string()]).unwrap_err();
+        assert_eq!(error, "Card Nowhere not found in program box_solution");
+    }
+}
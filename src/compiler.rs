@@ -0,0 +1,631 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::program::{Card, Direction, Instruction, Program};
+use crate::file_error::{FileError, FileErrors};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum TokenKind {
+    Word(String),
+    Text(String),
+    Open,
+    Close,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: impl Into<String>) -> FileError {
+        FileError::at(self.line, self.column, message)
+    }
+
+    fn describe(&self) -> String {
+        match &self.kind {
+            TokenKind::Word(word) => word.clone(),
+            TokenKind::Text(text) => format!("\"{}\"", text),
+            TokenKind::Open => "{".to_string(),
+            TokenKind::Close => "}".to_string(),
+        }
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word(word.to_string())
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, FileErrors> {
+    let mut tokens = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let column = i + 1;
+            let token = |kind| Token { kind, line: number, column };
+            match chars[i] {
+                c if c.is_whitespace() => i += 1,
+                '#' => break,
+                '{' => {
+                    tokens.push(token(TokenKind::Open));
+                    i += 1;
+                }
+                '}' => {
+                    tokens.push(token(TokenKind::Close));
+                    i += 1;
+                }
+                '"' => {
+                    let start = i + 1;
+                    let Some(length) = chars[start..].iter().position(|&c| c == '"') else {
+                        return Err(FileError::at(number, column, "Text is missing its closing \"").into());
+                    };
+                    tokens.push(token(TokenKind::Text(chars[start..start + length].iter().collect())));
+                    i = start + length + 1;
+                }
+                _ => {
+                    let start = i;
+                    while i < chars.len() && !chars[i].is_whitespace() && !"{}\"#".contains(chars[i]) {
+                        i += 1;
+                    }
+                    tokens.push(token(TokenKind::Word(chars[start..i].iter().collect())));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+const STATEMENTS: [&str; 8] = ["write", "move", "halt", "if", "while", "block", "break", "continue"];
+
+enum Statement {
+    Write { symbol: bool, line: usize },
+    Move { direction: Direction, count: usize, line: usize },
+    Halt,
+    If { lit: bool, then: Vec<Statement>, otherwise: Vec<Statement>, line: usize },
+    While { lit: bool, body: Vec<Statement>, line: usize },
+    Block { label: String, body: Vec<Statement> },
+    /// Leaves the loop or block at this index of the enclosing ones, counted from the outside
+    Break { scope: usize },
+    /// Starts over the loop or block at this index of the enclosing ones, counted from the outside
+    Continue { scope: usize },
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+    /// The loops and blocks around the statement being parsed, with the labels of the blocks
+    scopes: Vec<Option<String>>,
+    /// Line and column just after the last character of the text
+    end: (usize, usize),
+    /// Mistakes in statements that were skipped to read on
+    errors: Vec<FileError>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    /// The next token, if it is on the same line as `token`
+    fn peek_on_line(&self, token: &Token) -> Option<&Token> {
+        self.peek().filter(|next| next.line == token.line)
+    }
+
+    fn expected(&self, what: &str) -> FileError {
+        match self.peek() {
+            Some(token) => token.error(format!("Expected {} but found {}", what, token.describe())),
+            None => FileError::at(self.end.0, self.end.1, format!("Expected {} at the end of the program", what)),
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<(String, Token), FileError> {
+        match self.peek() {
+            Some(token @ Token { kind: TokenKind::Word(word), .. }) => {
+                let result = (word.clone(), token.clone());
+                self.index += 1;
+                Ok(result)
+            }
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    fn header(&mut self) -> Result<(String, String), FileError> {
+        if !self.peek().is_some_and(|token| token.is_word("program")) {
+            return Err(self.expected("program and the name of the program"));
+        }
+        self.index += 1;
+        let name = match self.peek() {
+            Some(Token { kind: TokenKind::Word(name) | TokenKind::Text(name), .. }) => name.clone(),
+            _ => return Err(self.expected("the name of the program")),
+        };
+        self.index += 1;
+        let mut description = String::new();
+        if self.peek().is_some_and(|token| token.is_word("description")) {
+            self.index += 1;
+            match self.peek() {
+                Some(Token { kind: TokenKind::Text(text), .. }) => description = text.clone(),
+                _ => return Err(self.expected("the description in quotes")),
+            }
+            self.index += 1;
+        }
+        Ok((name, description))
+    }
+
+    /// Statements up to the closing brace of a block, or to the end of the program if `open` is `None`
+    fn statements(&mut self, open: Option<&Token>) -> Result<Vec<Statement>, FileError> {
+        let mut statements = Vec::new();
+        loop {
+            match (self.peek(), open) {
+                (None, None) => return Ok(statements),
+                (None, Some(open)) => return Err(open.error("This { is never closed")),
+                (Some(Token { kind: TokenKind::Close, .. }), Some(_)) => {
+                    self.index += 1;
+                    return Ok(statements);
+                }
+                (Some(token), _) => {
+                    let line = token.line;
+                    match self.statement() {
+                        Ok(statement) => statements.push(statement),
+                        Err(error) if self.skip_line(line) => self.errors.push(error),
+                        Err(error) => return Err(error),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Skips the rest of a line with a mistake to read on from the next one, unless that would
+    /// skip braces and lose track of the blocks
+    fn skip_line(&mut self, line: usize) -> bool {
+        while let Some(token) = self.peek().filter(|token| token.line == line) {
+            if matches!(token.kind, TokenKind::Open | TokenKind::Close) {
+                return false;
+            }
+            self.index += 1;
+        }
+        true
+    }
+
+    /// Statements between braces
+    fn block(&mut self) -> Result<Vec<Statement>, FileError> {
+        let open = match self.peek() {
+            Some(token) if token.kind == TokenKind::Open => token.clone(),
+            _ => return Err(self.expected("{")),
+        };
+        self.index += 1;
+        self.statements(Some(&open))
+    }
+
+    /// Statements between braces that `break` and `continue` can refer to
+    fn scoped_block(&mut self, scope: Option<String>) -> Result<Vec<Statement>, FileError> {
+        self.scopes.push(scope);
+        let statements = self.block();
+        self.scopes.pop();
+        statements
+    }
+
+    fn condition(&mut self) -> Result<bool, FileError> {
+        let negated = self.peek().is_some_and(|token| token.is_word("not"));
+        if negated {
+            self.index += 1;
+        }
+        match self.peek() {
+            Some(token) if token.is_word("lit") => {
+                self.index += 1;
+                Ok(!negated)
+            }
+            _ => Err(self.expected(if negated { "lit" } else { "lit or not lit" })),
+        }
+    }
+
+    /// The loop or block a `break` or `continue` refers to, by label or else the innermost loop.
+    /// Only a name on the same line is a label, so `{ break }` and `break move right` leave the loop.
+    fn scope(&mut self, keyword: &Token) -> Result<usize, FileError> {
+        let label = match self.peek_on_line(keyword) {
+            Some(token @ Token { kind: TokenKind::Word(word), .. }) if !STATEMENTS.contains(&word.as_str()) => token.clone(),
+            _ => return self.scopes.iter().rposition(|scope| scope.is_none())
+                .ok_or(keyword.error(format!("{} is not inside a while loop, name a block to leave it", keyword.describe()))),
+        };
+        let (name, _) = self.word("the name of a block")?;
+        self.scopes.iter().rposition(|scope| scope.as_deref() == Some(name.as_str()))
+            .ok_or_else(|| label.error(format!("No block named {} around this {}", name, keyword.describe()))
+                .suggest(&name, self.scopes.iter().flatten().map(String::as_str)))
+    }
+
+    fn statement(&mut self) -> Result<Statement, FileError> {
+        let (keyword, token) = self.word("a statement")?;
+        let line = token.line;
+        match keyword.as_str() {
+            "write" => {
+                let symbol = match self.peek_on_line(&token) {
+                    Some(symbol) if symbol.is_word("1") => true,
+                    Some(symbol) if symbol.is_word("0") => false,
+                    _ => return Err(self.expected("0 or 1 to write")),
+                };
+                self.index += 1;
+                Ok(Statement::Write { symbol, line })
+            }
+            "move" => {
+                let direction = match self.peek_on_line(&token).map(|direction| direction.describe()).as_deref() {
+                    Some("left") => Direction::Left,
+                    Some("right") => Direction::Right,
+                    Some("up") => Direction::Up,
+                    Some("down") => Direction::Down,
+                    _ => {
+                        let error = self.expected("left, right, up or down");
+                        return Err(match self.peek_on_line(&token) {
+                            Some(direction) => error.suggest(&direction.describe(), ["left", "right", "up", "down"]),
+                            None => error,
+                        });
+                    }
+                };
+                self.index += 1;
+                let mut count = 1;
+                if let Some(Token { kind: TokenKind::Word(word), .. }) = self.peek_on_line(&token) {
+                    if let Ok(number) = word.parse::<usize>() {
+                        if number == 0 {
+                            return Err(self.expected("a number of cells above 0"));
+                        }
+                        count = number;
+                        self.index += 1;
+                    }
+                }
+                Ok(Statement::Move { direction, count, line })
+            }
+            "halt" => Ok(Statement::Halt),
+            "if" => {
+                let lit = self.condition()?;
+                let then = self.block()?;
+                let otherwise = if self.peek().is_some_and(|token| token.is_word("else")) {
+                    self.index += 1;
+                    if self.peek().is_some_and(|token| token.is_word("if")) {
+                        vec![self.statement()?]
+                    } else {
+                        self.block()?
+                    }
+                } else {
+                    Vec::new()
+                };
+                Ok(Statement::If { lit, then, otherwise, line })
+            }
+            "while" => {
+                let lit = self.condition()?;
+                let body = self.scoped_block(None)?;
+                Ok(Statement::While { lit, body, line })
+            }
+            "block" => {
+                let (label, label_token) = self.word("the name of the block")?;
+                if self.scopes.contains(&Some(label.clone())) {
+                    return Err(label_token.error(format!("Block {} is already inside a block of the same name", label)));
+                }
+                let body = self.scoped_block(Some(label.clone()))?;
+                Ok(Statement::Block { label, body })
+            }
+            "break" => Ok(Statement::Break { scope: self.scope(&token)? }),
+            "continue" => Ok(Statement::Continue { scope: self.scope(&token)? }),
+            _ => Err(token.error(format!("Expected a statement but found {}", keyword)).suggest(&keyword, STATEMENTS)),
+        }
+    }
+}
+
+/// A step of the compiled program, before the steps are packed into cards
+#[derive(Copy, Clone)]
+enum Node {
+    Halt,
+    /// Continues with another node, for loops and blocks whose start is only known later
+    Jump(usize),
+    /// Reads the cell under the head
+    Branch { on: usize, off: usize },
+    Act { write_symbol: Option<bool>, move_direction: Option<Direction>, next: usize },
+}
+
+const HALT: usize = 0;
+
+struct Compiler {
+    nodes: Vec<Node>,
+    /// The name each node would give a card starting at it
+    names: Vec<String>,
+    /// Where `break` and `continue` go for each enclosing loop or block
+    scopes: Vec<(usize, usize)>,
+}
+
+impl Compiler {
+    fn push(&mut self, node: Node, name: String) -> usize {
+        self.nodes.push(node);
+        self.names.push(name);
+        self.nodes.len() - 1
+    }
+
+    /// Compiles statements to continue with `next` after them
+    fn statements(&mut self, statements: &[Statement], next: usize) -> usize {
+        statements.iter().rev().fold(next, |next, statement| self.statement(statement, next))
+    }
+
+    fn statement(&mut self, statement: &Statement, next: usize) -> usize {
+        match statement {
+            Statement::Write { symbol, line } => {
+                self.push(Node::Act { write_symbol: Some(*symbol), move_direction: None, next }, format!("Line{}", line))
+            }
+            Statement::Move { direction, count, line } => (0..*count).fold(next, |next, _| {
+                self.push(Node::Act { write_symbol: None, move_direction: Some(*direction), next }, format!("Line{}", line))
+            }),
+            Statement::Halt => HALT,
+            Statement::If { lit, then, otherwise, line } => {
+                let then = self.statements(then, next);
+                let otherwise = self.statements(otherwise, next);
+                let (on, off) = if *lit { (then, otherwise) } else { (otherwise, then) };
+                self.push(Node::Branch { on, off }, format!("Line{}", line))
+            }
+            Statement::While { lit, body, line } => {
+                let start = self.push(Node::Jump(HALT), format!("Line{}", line));
+                self.scopes.push((next, start));
+                let body = self.statements(body, start);
+                self.scopes.pop();
+                let (on, off) = if *lit { (body, next) } else { (next, body) };
+                self.nodes[start] = Node::Branch { on, off };
+                start
+            }
+            Statement::Block { label, body, .. } => {
+                let start = self.push(Node::Jump(HALT), label.clone());
+                self.scopes.push((next, start));
+                let body = self.statements(body, next);
+                self.scopes.pop();
+                self.nodes[start] = Node::Jump(body);
+                start
+            }
+            Statement::Break { scope } => self.scopes[*scope].0,
+            Statement::Continue { scope } => self.scopes[*scope].1,
+        }
+    }
+
+    /// Follows jumps to the node they end up at, which names the card there after the outermost
+    /// block starting at it. A block that only starts itself over waits forever instead.
+    fn resolve_jumps(&mut self) {
+        for node in 0..self.nodes.len() {
+            let mut seen = HashSet::from([node]);
+            let mut target = node;
+            while let Node::Jump(next) = self.nodes[target] {
+                if !seen.insert(next) {
+                    self.nodes[next] = Node::Act { write_symbol: None, move_direction: None, next };
+                    break;
+                }
+                target = next;
+            }
+        }
+        for node in (0..self.nodes.len()).rev() {
+            if let Node::Jump(next) = self.nodes[node] {
+                let target = self.target(next);
+                self.names[target] = self.names[node].clone();
+            }
+        }
+    }
+
+    fn target(&self, mut node: usize) -> usize {
+        while let Node::Jump(next) = self.nodes[node] {
+            node = next;
+        }
+        node
+    }
+}
+
+/// Packs the nodes of a compiled program into cards, one for each node that a card starts at
+struct Packer<'a> {
+    compiler: &'a Compiler,
+    cards: Vec<Card>,
+    indices: HashMap<usize, usize>,
+    names: HashSet<String>,
+    unpacked: VecDeque<usize>,
+}
+
+impl Packer<'_> {
+    /// The card starting at a node, to be packed later if it is new
+    fn card(&mut self, node: usize) -> usize {
+        if let Some(&index) = self.indices.get(&node) {
+            return index;
+        }
+        let base = &self.compiler.names[node];
+        let mut name = base.clone();
+        for n in 2.. {
+            if !self.names.contains(&name) {
+                break;
+            }
+            name = format!("{}_{}", base, n);
+        }
+        self.names.insert(name.clone());
+        let mut card = Card::terminate();
+        card.name = name;
+        self.cards.push(card);
+        self.indices.insert(node, self.cards.len() - 1);
+        self.unpacked.push_back(node);
+        self.cards.len() - 1
+    }
+
+    fn pack(&mut self, node: usize) {
+        let (on, off) = match self.compiler.nodes[node] {
+            Node::Branch { on, off } => (on, off),
+            _ => (node, node),
+        };
+        let index = self.indices[&node];
+        self.cards[index].tape_on = self.instruction(on, true);
+        self.cards[index].tape_off = self.instruction(off, false);
+    }
+
+    /// Takes as many nodes as fit in one instruction, starting at `node` with `symbol` under the
+    /// head. While the head stays put the symbol under it is known, so reading it again takes no card.
+    fn instruction(&mut self, mut node: usize, symbol: bool) -> Instruction {
+        let mut known = Some(symbol);
+        let mut write_symbol = None;
+        let mut move_direction = None;
+        let mut seen = HashSet::new();
+        loop {
+            node = self.compiler.target(node);
+            if !seen.insert(node) {
+                break;
+            }
+            match self.compiler.nodes[node] {
+                Node::Halt => {
+                    return Instruction { write_symbol, move_direction, next_card: None, other_tapes: Vec::new() };
+                }
+                Node::Branch { on, off } => match known {
+                    Some(symbol) if move_direction.is_none() => node = if symbol { on } else { off },
+                    _ => break,
+                },
+                Node::Act { write_symbol: write, move_direction: direction, next } => {
+                    if move_direction.is_some() && (write.is_some() || direction.is_some()) {
+                        break;
+                    }
+                    if let Some(write) = write {
+                        if known != Some(write) {
+                            write_symbol = Some(write);
+                            known = Some(write);
+                        }
+                    }
+                    if direction.is_some() {
+                        move_direction = direction;
+                        known = None;
+                    }
+                    node = next;
+                }
+                Node::Jump(_) => unreachable!("Jumps are resolved"),
+            }
+        }
+        Instruction { write_symbol, move_direction, next_card: Some(self.card(node)), other_tapes: Vec::new() }
+    }
+}
+
+/// Compiles a program written in a small structured language to cards:
+///
+/// ```text
+/// program unary_addition
+/// description "Fill the gap between the numbers, then remove the last lit cell"
+///
+/// while lit { move right }
+/// write 1
+/// while lit { move right }
+/// move left
+/// write 0
+/// ```
+///
+/// Statements are `write 0` or `write 1`, `move` left, right, up or down and an optional number of
+/// cells, `halt`, `if lit { .. } else { .. }` and `while lit { .. }`, where `not lit` tests for an
+/// unlit cell. `block name { .. }` names statements so that `break name` can leave them and
+/// `continue name` can start them over, while a `break` or `continue` without a name refers to the
+/// innermost while loop. The program halts after its last statement.
+///
+/// Writes and moves share a card with the read before them where they fit, and tests of a cell
+/// whose symbol is already known take no card at all.
+pub fn compile(text: &str) -> Result<Program, FileErrors> {
+    let end = text.lines().enumerate().last().map_or((1, 1), |(index, line)| (index + 1, line.chars().count() + 1));
+    let mut parser = Parser { tokens: tokenize(text)?, index: 0, scopes: Vec::new(), end, errors: Vec::new() };
+    let (name, description) = parser.header()?;
+    let statements = parser.statements(None);
+    let mut errors = parser.errors;
+    let statements = match statements {
+        Ok(statements) if errors.is_empty() => statements,
+        Ok(_) => return Err(errors.into()),
+        Err(error) => {
+            errors.push(error);
+            return Err(errors.into());
+        }
+    };
+
+    let mut compiler = Compiler { nodes: Vec::new(), names: Vec::new(), scopes: Vec::new() };
+    compiler.push(Node::Halt, "Halt".to_string());
+    let start = compiler.statements(&statements, HALT);
+    compiler.resolve_jumps();
+
+    let mut packer = Packer {
+        compiler: &compiler,
+        cards: Vec::new(),
+        indices: HashMap::new(),
+        names: HashSet::new(),
+        unpacked: VecDeque::new(),
+    };
+    let start = compiler.target(start);
+    packer.card(start);
+    while let Some(node) = packer.unpacked.pop_front() {
+        packer.pack(node);
+    }
+    Ok(Program { name, description, initial_card: 0, cards: packer.cards })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::compile;
+    use crate::execution::LevelExecution;
+    use crate::levels;
+    use crate::program::{Direction, Program};
+
+    fn summary(program: &Program) -> Vec<String> {
+        let name = |next: Option<usize>| next.map_or("halt".to_string(), |next| program.cards[next].name.clone());
+        program.cards.iter()
+            .map(|card| format!("{}: on {:?} {:?} {}; off {:?} {:?} {}",
+                                card.name,
+                                card.tape_on.write_symbol, card.tape_on.move_direction, name(card.tape_on.next_card),
+                                card.tape_off.write_symbol, card.tape_off.move_direction, name(card.tape_off.next_card)))
+            .collect()
+    }
+
+    #[test]
+    fn compiles_unary_addition() {
+        let program = compile(include_str!("../res/program/unary_addition.tl")).unwrap();
+        assert_eq!(summary(&program), vec![
+            "Line5: on None Some(Right) Line5; off Some(true) Some(Right) Line9",
+            "Line9: on None Some(Right) Line9; off None Some(Left) Line11",
+            "Line11: on Some(false) None halt; off None None halt",
+        ]);
+        let level = levels::builtins().into_iter().find(|level| level.name == "addition").unwrap();
+        let mut execution = LevelExecution::new(level, program);
+        execution.run(1_000);
+        assert!(execution.is_solved());
+    }
+
+    #[test]
+    fn known_symbols_take_no_card() {
+        let program = compile("program p\nwhile lit {\n  if not lit { halt } else { write 1 }\n  move right 2\n}").unwrap();
+        assert_eq!(summary(&program), vec![
+            "Line2: on None Some(Right) Line4; off None None halt",
+            "Line4: on None Some(Right) Line2; off None Some(Right) Line2",
+        ]);
+    }
+
+    #[test]
+    fn blocks_are_left_and_started_over() {
+        let text = "program p\nblock search {\n  while lit { move right }\n  move right\n  if lit { continue search }\n}\nwrite 1";
+        let program = compile(text).unwrap();
+        assert_eq!(summary(&program), vec![
+            "search: on None Some(Right) search; off None Some(Right) Line5",
+            "Line5: on None Some(Right) search; off Some(true) None halt",
+        ]);
+
+        let program = compile("program p\nblock wait { continue wait }").unwrap();
+        assert_eq!(summary(&program), vec!["wait: on None None wait; off None None wait"]);
+        let program = compile("program p\nwhile lit { if lit { break } move right }").unwrap();
+        assert_eq!(summary(&program), vec!["Line2: on None None halt; off None None halt"]);
+        assert_eq!(compile("program p\nmove up").unwrap().cards[0].tape_on.move_direction, Some(Direction::Up));
+    }
+
+    #[test]
+    fn errors_point_at_the_mistake() {
+        let error = |text: &str| compile(text).err().unwrap().to_string();
+        assert_eq!(error("move right"), "line 1, column 1: Expected program and the name of the program but found move");
+        assert_eq!(error("program p\nwhile lit {\n  move right\n"), "line 2, column 11: This { is never closed");
+        assert_eq!(error("program p\nbreak"), "line 2, column 1: break is not inside a while loop, name a block to leave it");
+        assert_eq!(error("program p\nblock loop { while lit { continue lop } }"), "line 2, column 35: No block named lop around this continue. Did you mean loop?");
+        assert_eq!(error("program p\nif lit { mvoe left }"), "line 2, column 10: Expected a statement but found mvoe. Did you mean move?");
+        assert_eq!(error("program p\nwrite 2"), "line 2, column 7: Expected 0 or 1 to write but found 2");
+    }
+
+    #[test]
+    fn every_mistake_is_reported() {
+        let text = "program p\nwhile lit {\n  move rihgt\n  write 2\n}\nhalt now";
+        assert_eq!(compile(text).err().unwrap().to_string(), [
+            "line 3, column 8: Expected left, right, up or down but found rihgt. Did you mean right?",
+            "line 4, column 9: Expected 0 or 1 to write but found 2",
+            "line 6, column 6: Expected a statement but found now",
+        ].join("\n"));
+    }
+}
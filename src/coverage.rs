@@ -0,0 +1,119 @@
+use serde::Serialize;
+
+use crate::level::Level;
+use crate::profile::profile;
+use crate::program::Program;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct InstructionCoverage {
+    pub card: String,
+    /// Whether each head reads a lit cell for this instruction
+    pub read: Vec<bool>,
+    /// 0 for the instruction of the card, or the place of the alternative among the choices
+    pub choice: usize,
+    /// Indices of the test cases that executed the instruction at least once
+    pub cases: Vec<usize>,
+    /// How many times the instruction was executed over all test cases
+    pub executions: u64,
+}
+
+impl InstructionCoverage {
+    pub fn is_covered(&self) -> bool {
+        !self.cases.is_empty()
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Coverage {
+    pub level: String,
+    pub program: String,
+    /// Every instruction of every card, in the order of the cards and `Card::all_instructions`
+    pub instructions: Vec<InstructionCoverage>,
+    /// Indices of the test cases that did not halt, so may not have covered everything they would
+    pub unterminated: Vec<usize>,
+}
+
+impl Coverage {
+    pub fn uncovered(&self) -> impl Iterator<Item=&InstructionCoverage> {
+        self.instructions.iter().filter(|instruction| !instruction.is_covered())
+    }
+
+    pub fn covered_count(&self) -> usize {
+        self.instructions.len() - self.uncovered().count()
+    }
+}
+
+/// Runs the program on every test case of the level, giving each at most `max_steps` steps,
+/// and records which card instructions each test case executed.
+pub fn coverage(level: Level, program: Program, max_steps: u64) -> Coverage {
+    let (execution, profiles) = profile(level, program, max_steps);
+    let tapes = execution.program.tapes();
+    let instructions = execution.program.cards.iter()
+        .enumerate()
+        .flat_map(|(card_index, card)| card.all_instructions().enumerate()
+            .map(move |(index, (read, choice, _))| (card_index, card, index, read, choice)))
+        .map(|(card_index, card, index, read, choice)| {
+            let counts: Vec<u64> = profiles.iter().map(|profile| profile.instructions[card_index][index]).collect();
+            InstructionCoverage {
+                card: card.name.clone(),
+                read: (0..tapes).map(|tape| read >> tape & 1 == 1).collect(),
+                choice,
+                cases: counts.iter().enumerate().filter(|(_, &count)| count > 0).map(|(index, _)| index).collect(),
+                executions: counts.iter().sum(),
+            }
+        })
+        .collect();
+    let unterminated = execution.executions.iter()
+        .enumerate()
+        .filter(|(_, tce)| !tce.is_terminated())
+        .map(|(index, _)| index)
+        .collect();
+    Coverage {
+        level: execution.level.name,
+        program: execution.program.name,
+        instructions,
+        unterminated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coverage::coverage;
+    use crate::levels;
+    use crate::programs;
+
+    #[test]
+    fn addition_never_removes_from_an_unlit_cell() {
+        let level = levels::builtins().into_iter().find(|level| level.name == "addition").unwrap();
+        let program = programs::builtins().into_iter().find(|program| program.name == "addition_solution").unwrap();
+        let coverage = coverage(level, program, 10_000);
+        assert!(coverage.unterminated.is_empty());
+        assert_eq!(coverage.instructions.len(), 6);
+        assert_eq!(coverage.covered_count(), 5);
+        let uncovered: Vec<(&str, &[bool])> = coverage.uncovered().map(|instruction| (instruction.card.as_str(), &instruction.read[..])).collect();
+        assert_eq!(uncovered, vec![("Remove", &[false][..])]);
+        let first_on = &coverage.instructions[1];
+        assert_eq!((first_on.card.as_str(), &first_on.read[..]), ("First", &[true][..]));
+        assert_eq!(first_on.cases, vec![0, 2, 4]);
+        assert_eq!(first_on.executions, 8);
+    }
+
+    #[test]
+    fn every_instruction_of_more_tapes_and_alternatives_is_listed() {
+        let level = levels::builtins().into_iter().find(|level| level.name == "copy").unwrap();
+        let program = programs::builtins().into_iter().find(|program| program.name == "copy_solution").unwrap();
+        let instructions: usize = program.cards.iter().map(|card| card.instructions().count()).sum();
+        let multi_tape = coverage(level, program, 10_000);
+        assert_eq!(multi_tape.instructions.len(), instructions);
+        assert!(multi_tape.instructions.iter().all(|instruction| instruction.read.len() == 2));
+        let reads: Vec<&[bool]> = multi_tape.instructions[..4].iter().map(|instruction| &instruction.read[..]).collect();
+        assert_eq!(reads, vec![&[false, false][..], &[true, false], &[false, true], &[true, true]]);
+
+        let level = levels::builtins().into_iter().find(|level| level.name == "lucky_moth").unwrap();
+        let program = programs::builtins().into_iter().find(|program| program.name == "lucky_moth_solution").unwrap();
+        let nondeterministic = coverage(level, program, 10_000);
+        assert_eq!(nondeterministic.instructions.len(), 7);
+        let alternative = &nondeterministic.instructions[2];
+        assert_eq!((alternative.card.as_str(), &alternative.read[..], alternative.choice), ("Guess", &[false][..], 1));
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::fs;
+use std::fs::{create_dir_all, read_dir};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{Error, Result};
+use directories::ProjectDirs;
+
+use crate::level::Level;
+use crate::levels;
+use crate::loader;
+use crate::pack::{Pack, PackEntry};
+use crate::packs;
+use crate::program::Program;
+use crate::programs;
+use crate::progress::Progress;
+
+/// The directory with the programs, levels and packs of a player, and their progress.
+/// Subdirectories are created when they are first needed.
+#[derive(Clone, Debug)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+/// A file or directory that could not be read, named after its file name
+#[derive(Clone, Debug)]
+pub struct Unreadable {
+    pub name: String,
+    pub error: String,
+}
+
+impl Unreadable {
+    fn new(path: &Path, error: impl ToString) -> Unreadable {
+        Unreadable {
+            name: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+impl DataDir {
+    pub fn new(root: impl Into<PathBuf>) -> DataDir {
+        DataDir { root: root.into() }
+    }
+
+    /// The data directory of the current user
+    pub fn user() -> Result<DataDir> {
+        let dirs = ProjectDirs::from("org", "simonolander", "Tur")
+            .ok_or(Error::msg("Unable to find suitable project directory"))?;
+        Ok(DataDir::new(dirs.data_dir()))
+    }
+
+    pub fn program_dir(&self) -> Result<PathBuf> {
+        self.subdir("program")
+    }
+
+    pub fn level_dir(&self) -> Result<PathBuf> {
+        self.subdir("level")
+    }
+
+    pub fn pack_dir(&self) -> Result<PathBuf> {
+        self.subdir("pack")
+    }
+
+    fn subdir(&self, name: &str) -> Result<PathBuf> {
+        let buf = self.root.join(name);
+        if !buf.exists() {
+            create_dir_all(&buf)?;
+        }
+        Ok(buf)
+    }
+
+    fn progress_file(&self) -> PathBuf {
+        self.root.join("progress.yaml")
+    }
+
+    pub fn load_progress(&self) -> Result<Progress> {
+        let path = self.progress_file();
+        if !path.exists() {
+            return Ok(Progress::default());
+        }
+        Ok(serde_yaml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save_progress(&self, progress: &Progress) -> Result<()> {
+        create_dir_all(&self.root)?;
+        fs::write(self.progress_file(), serde_yaml::to_string(progress)?)?;
+        Ok(())
+    }
+
+    /// The yaml file a new custom program with this name is written to
+    pub fn program_file(&self, name: &str) -> Result<PathBuf> {
+        let dir = self.program_dir()?;
+        let path = dir.join(format!("{}.yaml", name));
+        let parent = path.parent()
+            .ok_or(Error::msg(format!("Could not get parent of path {}", path.to_string_lossy())))?;
+        if parent == dir.as_path() {
+            Ok(path)
+        } else {
+            Err(Error::msg(format!("Mismatched parents: expected {} but was {}", dir.to_string_lossy(), parent.to_string_lossy())))
+        }
+    }
+
+    /// The file of a custom program in any of the formats programs can be written in, if it exists
+    pub fn existing_program_file(&self, name: &str) -> Result<Option<PathBuf>> {
+        let path = self.program_file(name)?;
+        Ok(loader::PROGRAM_EXTENSIONS.iter().map(|extension| path.with_extension(extension)).find(|path| path.is_file()))
+    }
+
+    /// The custom program with this name, or else the builtin one
+    pub fn find_program(&self, name: &str) -> Result<Option<Program>> {
+        if let Some(path) = self.existing_program_file(name)? {
+            return Ok(Some(loader::read_program(&path)?));
+        }
+        Ok(programs::builtins().into_iter().find(|program| program.name == name))
+    }
+
+    /// The installed packs that can be read, and the others
+    pub fn installed_packs(&self) -> Result<(Vec<Pack>, Vec<Unreadable>)> {
+        let mut packs = Vec::new();
+        let mut unreadable = Vec::new();
+        for entry in read_dir(self.pack_dir()?)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                match loader::read_pack(&path) {
+                    Ok(pack) => packs.push(pack),
+                    Err(err) => unreadable.push(Unreadable::new(&path, err)),
+                }
+            }
+        }
+        Ok((packs, unreadable))
+    }
+
+    /// The custom levels that can be read, and the others
+    pub fn custom_levels(&self) -> Result<(Vec<Level>, Vec<Unreadable>)> {
+        let mut levels = Vec::new();
+        let mut unreadable = Vec::new();
+        for path in loader::level_files(&self.level_dir()?)? {
+            match loader::read_level(&path) {
+                Ok(level) => levels.push(level),
+                Err(err) => unreadable.push(Unreadable::new(&path, err)),
+            }
+        }
+        Ok((levels, unreadable))
+    }
+
+    /// The builtin levels, then those of installed packs, then the custom ones
+    pub fn all_levels(&self) -> Result<Vec<Level>> {
+        // Levels that cannot be read are listed by level list, and not found here
+        let installed = self.installed_packs()?.0
+            .into_iter()
+            .flat_map(|pack| pack.entries)
+            .map(|entry| entry.level);
+        // Packs may refer to builtin levels, and the first level with a name hides any later ones
+        let mut names = HashSet::new();
+        Ok(levels::builtins()
+            .into_iter()
+            .chain(installed)
+            .chain(self.custom_levels()?.0)
+            .filter(|level| names.insert(level.name.clone()))
+            .collect())
+    }
+
+    pub fn find_level(&self, level_name: &str) -> Result<Option<Level>> {
+        Ok(self.all_levels()?.into_iter().find(|level| level.name == level_name))
+    }
+
+    /// The levels that still have to be solved before this one can be played. Levels that are in
+    /// no pack, or that one of their packs unlocks, can always be played.
+    pub fn missing_requirements(&self, level_name: &str, progress: &Progress) -> Result<Vec<String>> {
+        let entries: Vec<PackEntry> = packs::builtins()
+            .into_iter()
+            .chain(self.installed_packs()?.0)
+            .flat_map(|pack| pack.entries)
+            .filter(|entry| entry.level.name == level_name)
+            .collect();
+        if entries.iter().any(|entry| entry.is_unlocked(progress)) {
+            return Ok(Vec::new());
+        }
+        Ok(entries.first()
+            .map(|entry| entry.requires.iter().filter(|name| !progress.is_solved(name)).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// Installs a pack from its directory, or from a tar archive of it, replacing any installed
+    /// pack with the same name
+    pub fn install_pack(&self, path: &Path) -> Result<Pack> {
+        if path.is_dir() {
+            return self.install_pack_dir(path);
+        }
+        let extract_dir = std::env::temp_dir().join(format!("tur-pack-{}", process::id()));
+        create_dir_all(&extract_dir)?;
+        let status = process::Command::new("tar")
+            .arg("-xf")
+            .arg(path)
+            .arg("-C")
+            .arg(&extract_dir)
+            .status()?;
+        let result = if !status.success() {
+            Err(Error::msg(format!("Could not extract {}", path.to_string_lossy())))
+        } else if extract_dir.join("pack.yaml").exists() {
+            self.install_pack_dir(&extract_dir)
+        } else {
+            let subdirs = read_dir(&extract_dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<PathBuf>>>()?;
+            match subdirs.as_slice() {
+                [subdir] if subdir.join("pack.yaml").exists() => self.install_pack_dir(subdir),
+                _ => Err(Error::msg(format!("No pack.yaml found in {}", path.to_string_lossy()))),
+            }
+        };
+        fs::remove_dir_all(&extract_dir)?;
+        result
+    }
+
+    /// Copies the pack next to the installed packs first and only then swaps it in, so that a
+    /// pack can be installed from its own installed directory.
+    fn install_pack_dir(&self, dir: &Path) -> Result<Pack> {
+        let pack = loader::read_pack(dir)?;
+        let pack_dir = self.pack_dir()?;
+        let target = pack_dir.join(&pack.name);
+        // Starts with a dot, so that it is never the name of a pack
+        let staging = pack_dir.join(format!(".{}-{}", pack.name, process::id()));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        create_dir_all(&staging)?;
+        for entry in read_dir(dir)? {
+            let entry = entry?;
+            // Symbolic links could make the pack copy files from anywhere
+            let is_file = entry.file_type()?.is_file();
+            if is_file && entry.path().extension().is_some_and(|extension| extension == "yaml") {
+                fs::copy(entry.path(), staging.join(entry.file_name()))?;
+            }
+        }
+        if target.exists() {
+            let root = pack_dir.canonicalize()?;
+            let canonical = target.canonicalize()?;
+            if !canonical.starts_with(&root) || canonical == root {
+                fs::remove_dir_all(&staging)?;
+                return Err(Error::msg(format!("Pack {} is not installed in {}", pack.name, root.display())));
+            }
+            fs::remove_dir_all(&canonical)?;
+        }
+        fs::rename(&staging, &target)?;
+        Ok(pack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::process;
+
+    use crate::data_dir::DataDir;
+    use crate::program_dto::ProgramDto;
+    use crate::programs;
+    use crate::progress::Progress;
+
+    fn write(path: &Path, text: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn packs_are_installed_next_to_unreadable_ones() {
+        let root = std::env::temp_dir().join(format!("tur-data-dir-{}", process::id()));
+        let data = DataDir::new(&root);
+        let source = root.join("source");
+        write(&source.join("pack.yaml"), "name: firsts\ndescription: The first levels\nlevels:\n  - level: night_time\n");
+        write(&root.join("pack/broken/pack.yaml"), "name: [");
+
+        let pack = data.install_pack(&source).unwrap();
+        let (packs, unreadable) = data.installed_packs().unwrap();
+        let level = data.find_level("night_time").unwrap();
+        let mut progress = Progress::default();
+        progress.set_solved("night_time");
+        data.save_progress(&progress).unwrap();
+        let loaded = data.load_progress().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(pack.name, "firsts");
+        assert_eq!(packs.iter().map(|pack| pack.name.as_str()).collect::<Vec<_>>(), vec!["firsts"]);
+        assert_eq!(unreadable.iter().map(|unreadable| unreadable.name.as_str()).collect::<Vec<_>>(), vec!["broken"]);
+        assert!(level.is_some());
+        assert!(loaded.is_solved("night_time"));
+    }
+
+    #[test]
+    fn packs_can_be_installed_from_where_they_are_installed() {
+        let root = std::env::temp_dir().join(format!("tur-data-dir-reinstall-{}", process::id()));
+        let data = DataDir::new(&root);
+        let source = root.join("source");
+        write(&source.join("pack.yaml"), "name: firsts\nlevels:\n  - level: night_time\n");
+        let outside = Path::new(env!("CARGO_MANIFEST_DIR")).join("res/level/sandbox.yaml");
+        std::os::unix::fs::symlink(outside, source.join("outside.yaml")).unwrap();
+
+        data.install_pack(&source).unwrap();
+        let installed = root.join("pack/firsts");
+        let reinstalled = data.install_pack(&installed);
+        let files = fs::read_dir(&installed).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let packs = fs::read_dir(root.join("pack")).unwrap().count();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(reinstalled.unwrap().name, "firsts");
+        assert_eq!(files, vec!["pack.yaml"]);
+        assert_eq!(packs, 1);
+    }
+
+    #[test]
+    fn programs_are_found_in_the_same_file_they_are_edited_in() {
+        let root = std::env::temp_dir().join(format!("tur-data-dir-programs-{}", process::id()));
+        let data = DataDir::new(&root);
+        write(&root.join("program/both.tur"), "this is not a program");
+        let yaml = programs::builtins().into_iter().find(|program| program.name == "just_stop").unwrap();
+        write(&root.join("program/both.yaml"), &serde_yaml::to_string(&ProgramDto::from(yaml)).unwrap());
+
+        let file = data.existing_program_file("both").unwrap();
+        let program = data.find_program("both").unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(file, Some(root.join("program/both.yaml")));
+        assert_eq!(program.unwrap().name, "just_stop");
+    }
+
+    #[test]
+    fn levels_are_locked_until_any_of_their_packs_unlocks_them() {
+        let root = std::env::temp_dir().join(format!("tur-data-dir-locks-{}", process::id()));
+        let data = DataDir::new(&root);
+        write(&root.join("pack/shortcut/pack.yaml"), "name: shortcut\nlevels:\n  - level: sandbox\n  - level: moth\n    requires: [ sandbox ]\n");
+        let mut progress = Progress::default();
+
+        let locked = data.missing_requirements("moth", &progress).unwrap();
+        progress.set_solved("sandbox");
+        let unlocked = data.missing_requirements("moth", &progress).unwrap();
+        let tutorial = data.missing_requirements("addition", &progress).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(locked, vec!["night_time"]);
+        assert!(unlocked.is_empty());
+        assert_eq!(tutorial, vec!["increment"]);
+    }
+}
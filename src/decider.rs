@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::execution::TestCaseExecution;
+use crate::level::TestCase;
+use crate::macro_execution::MacroExecution;
+use crate::program::{Direction, Program};
+
+pub enum Verdict {
+    /// The program halts by executing the instruction of `card` for a tape that is `on`.
+    Halts { steps: u64, lit: usize, card: usize, on: bool },
+    NeverHalts,
+}
+
+/// Decides, if it can, whether a program halts when started on an empty tape.
+pub trait Decider {
+    fn name(&self) -> &'static str;
+
+    fn decide(&self, program: &Program) -> Option<Verdict>;
+}
+
+pub fn builtins(max_steps: u64) -> Vec<Box<dyn Decider>> {
+    vec![
+        Box::new(StepLimit { max_steps }),
+        Box::new(Cycler { max_steps }),
+        Box::new(TranslatedCycler { max_steps }),
+        Box::new(BackwardReasoning { depth: 20, max_configurations: 10_000 }),
+    ]
+}
+
+fn empty_tape(program: &Program) -> TestCaseExecution {
+    TestCaseExecution::new(TestCase::default(), program.clone())
+}
+
+/// Runs the program for at most `max_steps` steps and reports if it halted.
+/// Sweeps over the tape are accelerated, so the step limit can be very large.
+pub struct StepLimit {
+    pub max_steps: u64,
+}
+
+impl Decider for StepLimit {
+    fn name(&self) -> &'static str {
+        "step limit"
+    }
+
+    fn decide(&self, program: &Program) -> Option<Verdict> {
+        let mut execution = MacroExecution::new(TestCase::default(), program.clone());
+        if !execution.run(self.max_steps) {
+            return None;
+        }
+        let (card, on) = execution.get_halted_on()?;
+        Some(Verdict::Halts { steps: execution.steps, lit: execution.count_lit() as usize, card, on })
+    }
+}
+
+/// Detects programs that return to an earlier configuration of card, position and tape.
+pub struct Cycler {
+    pub max_steps: u64,
+}
+
+impl Decider for Cycler {
+    fn name(&self) -> &'static str {
+        "cycler"
+    }
+
+    fn decide(&self, program: &Program) -> Option<Verdict> {
+        let mut tce = empty_tape(program);
+        let mut snapshot: Option<(usize, i64, HashSet<i64>)> = None;
+        let mut checkpoint = 1;
+        while let Some(card) = tce.get_current_card_index() {
+            if tce.steps >= self.max_steps {
+                return None;
+            }
+            let position = tce.get_current_position();
+            if let Some((snapshot_card, snapshot_position, snapshot_tape)) = &snapshot {
+                if *snapshot_card == card && *snapshot_position == position && *snapshot_tape == tce.positions_on {
+                    return Some(Verdict::NeverHalts);
+                }
+            }
+            if tce.steps == checkpoint {
+                snapshot = Some((card, position, tce.positions_on.clone()));
+                checkpoint *= 2;
+            }
+            tce.step();
+        }
+        None
+    }
+}
+
+/// Detects programs that repeat the same behaviour further and further out on the tape.
+///
+/// Whenever the head visits a cell further out than ever before, the configuration is recorded.
+/// If an earlier record has the same card, and the tape between the leftmost position visited
+/// since that record and the head looks the same now as it did then, the program will keep
+/// repeating the same steps shifted along the tape.
+pub struct TranslatedCycler {
+    pub max_steps: u64,
+}
+
+struct Record {
+    card: usize,
+    position: i64,
+    tape: HashSet<i64>,
+    /// Position furthest away from the direction of the records, visited since the record
+    furthest_back: i64,
+}
+
+const MAX_RECORDS: usize = 100;
+
+impl Decider for TranslatedCycler {
+    fn name(&self) -> &'static str {
+        "translated cycler"
+    }
+
+    fn decide(&self, program: &Program) -> Option<Verdict> {
+        let mut tce = empty_tape(program);
+        // Positions are multiplied by the sign, so records are always in the positive direction
+        let mut records: [(i64, i64, Vec<Record>); 2] = [(1, 0, Vec::new()), (-1, 0, Vec::new())];
+        while let Some(card) = tce.get_current_card_index() {
+            if tce.steps >= self.max_steps {
+                return None;
+            }
+            for (sign, furthest, records) in records.iter_mut() {
+                let position = *sign * tce.get_current_position();
+                for record in records.iter_mut() {
+                    record.furthest_back = record.furthest_back.min(position);
+                }
+                if position <= *furthest {
+                    continue;
+                }
+                *furthest = position;
+                let repeats = records.iter().any(|record| {
+                    let offset = position - record.position;
+                    record.card == card && (record.furthest_back..=record.position).all(|cell| {
+                        record.tape.contains(&(*sign * cell)) == tce.get_tape_at(*sign * (cell + offset))
+                    })
+                });
+                if repeats {
+                    return Some(Verdict::NeverHalts);
+                }
+                if records.len() == MAX_RECORDS {
+                    records.remove(0);
+                }
+                records.push(Record {
+                    card,
+                    position,
+                    tape: tce.positions_on.clone(),
+                    furthest_back: position,
+                });
+            }
+            tce.step();
+        }
+        None
+    }
+}
+
+/// Works backwards from every halting instruction, keeping track of what the tape around the
+/// head must have looked like. If no sequence of `depth` instructions can lead to a halt,
+/// the program never halts.
+pub struct BackwardReasoning {
+    pub depth: usize,
+    pub max_configurations: usize,
+}
+
+impl Decider for BackwardReasoning {
+    fn name(&self) -> &'static str {
+        "backward reasoning"
+    }
+
+    fn decide(&self, program: &Program) -> Option<Verdict> {
+        let instructions = |card: usize| {
+            let card = &program.cards[card];
+            [(false, &card.tape_off), (true, &card.tape_on)]
+        };
+
+        // Card about to execute, and the known cells relative to the head
+        let mut configurations: Vec<(usize, HashMap<i64, bool>)> = (0..program.cards.len())
+            .flat_map(|card| instructions(card).into_iter().map(move |(on, instruction)| (card, on, instruction)))
+            .filter(|(_, _, instruction)| instruction.next_card.is_none())
+            .map(|(card, on, _)| (card, HashMap::from([(0, on)])))
+            .collect();
+
+        for _ in 0..self.depth {
+            if configurations.is_empty() {
+                return Some(Verdict::NeverHalts);
+            }
+            let mut previous = Vec::new();
+            for (card, tape) in &configurations {
+                if *card == program.initial_card && tape.values().all(|on| !on) {
+                    return None;
+                }
+                for previous_card in 0..program.cards.len() {
+                    for (read, instruction) in instructions(previous_card) {
+                        if instruction.next_card != Some(*card) {
+                            continue;
+                        }
+                        let previous_head = match instruction.move_direction {
+                            Some(Direction::Left) => 1,
+                            Some(Direction::Right) => -1,
+                            Some(Direction::Up) | Some(Direction::Down) | None => 0,
+                        };
+                        let written = instruction.write_symbol.unwrap_or(read);
+                        if tape.get(&previous_head).is_some_and(|&on| on != written) {
+                            continue;
+                        }
+                        let mut previous_tape: HashMap<i64, bool> = tape.iter()
+                            .map(|(&cell, &on)| (cell - previous_head, on))
+                            .collect();
+                        previous_tape.insert(0, read);
+                        previous.push((previous_card, previous_tape));
+                    }
+                }
+            }
+            if previous.len() > self.max_configurations {
+                return None;
+            }
+            configurations = previous;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decider::{BackwardReasoning, Cycler, Decider, StepLimit, TranslatedCycler, Verdict};
+    use crate::program::{Card, Direction, Instruction, Program};
+    use crate::programs;
+
+    fn builtin(name: &str) -> Program {
+        programs::builtins().into_iter().find(|program| program.name == name).unwrap()
+    }
+
+    fn never_halts(decider: &dyn Decider, program: &Program) -> bool {
+        matches!(decider.decide(program), Some(Verdict::NeverHalts))
+    }
+
+    /// Bounces between two cells forever
+    fn bouncer() -> Program {
+        let instruction = |move_direction, next_card| Instruction {
+            write_symbol: Some(true),
+            move_direction: Some(move_direction),
+            next_card: Some(next_card),
+            other_tapes: Vec::new(),
+        };
+        Program {
+            name: "bouncer".to_string(),
+            description: String::new(),
+            initial_card: 0,
+            cards: vec![
+                Card {
+                    name: "A".to_string(),
+                    tape_on: instruction(Direction::Right, 1),
+                    tape_off: instruction(Direction::Right, 1),
+                    other_instructions: Vec::new(),
+                    alternatives: Vec::new(),
+                },
+                Card {
+                    name: "B".to_string(),
+                    tape_on: instruction(Direction::Left, 0),
+                    tape_off: instruction(Direction::Left, 0),
+                    other_instructions: Vec::new(),
+                    alternatives: Vec::new(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn step_limit() {
+        let decider = StepLimit { max_steps: 100 };
+        assert!(matches!(decider.decide(&builtin("just_stop")), Some(Verdict::Halts { steps: 1, lit: 0, card: 0, on: false })));
+        assert!(decider.decide(&builtin("go_right")).is_none());
+    }
+
+    #[test]
+    fn cycler() {
+        let decider = Cycler { max_steps: 100 };
+        assert!(never_halts(&decider, &bouncer()));
+        assert!(!never_halts(&decider, &builtin("go_right")));
+        assert!(!never_halts(&decider, &builtin("just_stop")));
+    }
+
+    #[test]
+    fn translated_cycler() {
+        let decider = TranslatedCycler { max_steps: 100 };
+        assert!(never_halts(&decider, &builtin("go_right")));
+        assert!(never_halts(&decider, &builtin("light_left")));
+        assert!(!never_halts(&decider, &builtin("light_the_world")));
+        assert!(!never_halts(&decider, &builtin("just_stop")));
+    }
+
+    #[test]
+    fn backward_reasoning() {
+        let decider = BackwardReasoning { depth: 10, max_configurations: 1000 };
+        assert!(never_halts(&decider, &builtin("go_right")));
+        assert!(never_halts(&decider, &bouncer()));
+        assert!(!never_halts(&decider, &builtin("just_stop")));
+        assert!(!never_halts(&decider, &builtin("night_time_solution")));
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use crate::encoding::Encoding::{Binary, Unary};
+
+/// How numbers are written onto the tape, starting at position 0.
+/// Multiple numbers are laid out left to right, separated by a single unlit cell.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum Encoding {
+    /// A run of `n` lit cells.
+    #[default]
+    Unary,
+    /// Little-endian bits, least significant bit at the lowest position.
+    /// Each number is padded with unlit cells to at least `width` cells.
+    Binary { width: Option<u32> },
+}
+
+impl Encoding {
+    pub fn encode(&self, numbers: &[u64]) -> HashSet<i64> {
+        let mut tape = HashSet::new();
+        let mut offset = 0;
+        for &number in numbers {
+            let length = self.length(number);
+            for i in 0..length {
+                if self.is_lit(number, i) {
+                    tape.insert(offset + i as i64);
+                }
+            }
+            offset += length as i64 + 1;
+        }
+        tape
+    }
+
+    fn length(&self, number: u64) -> u64 {
+        match self {
+            Unary => number,
+            Binary { width } => {
+                let bits = (u64::BITS - number.leading_zeros()) as u64;
+                bits.max(width.unwrap_or(0) as u64)
+            }
+        }
+    }
+
+    fn is_lit(&self, number: u64, index: u64) -> bool {
+        match self {
+            Unary => true,
+            Binary { .. } => index < u64::BITS as u64 && number >> index & 1 == 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::encoding::Encoding::{Binary, Unary};
+
+    fn tape(positions: &[i64]) -> HashSet<i64> {
+        positions.iter().copied().collect()
+    }
+
+    #[test]
+    fn unary() {
+        assert_eq!(Unary.encode(&[]), tape(&[]));
+        assert_eq!(Unary.encode(&[0]), tape(&[]));
+        assert_eq!(Unary.encode(&[3]), tape(&[0, 1, 2]));
+        assert_eq!(Unary.encode(&[2, 3]), tape(&[0, 1, 3, 4, 5]));
+        assert_eq!(Unary.encode(&[0, 2]), tape(&[1, 2]));
+    }
+
+    #[test]
+    fn binary() {
+        let binary = Binary { width: None };
+        assert_eq!(binary.encode(&[0]), tape(&[]));
+        assert_eq!(binary.encode(&[1]), tape(&[0]));
+        assert_eq!(binary.encode(&[6]), tape(&[1, 2]));
+        assert_eq!(binary.encode(&[3, 1]), tape(&[0, 1, 3]));
+    }
+
+    #[test]
+    fn binary_with_width() {
+        let binary = Binary { width: Some(4) };
+        assert_eq!(binary.encode(&[1, 1]), tape(&[0, 5]));
+        assert_eq!(binary.encode(&[0, 2]), tape(&[6]));
+        assert_eq!(binary.encode(&[31, 0]), tape(&[0, 1, 2, 3, 4]));
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::iter;
+
+use crate::level::TestCase;
+use crate::macro_execution::MacroExecution;
+use crate::program::{Direction, Instruction, Program, TapeAction};
+
+/// How a program ended up after running on some input.
+#[derive(Eq, PartialEq, Debug)]
+pub enum Ending {
+    Halted { position: i64, tape: Vec<i64> },
+    /// The head moved past the last cell of the tape at the position
+    FellOff { position: i64 },
+    Running,
+}
+
+pub struct BehaviourDifference {
+    pub input: Vec<i64>,
+    pub a: Ending,
+    pub b: Ending,
+}
+
+/// Runs both programs on every input, and returns the first input they end differently on.
+/// Inputs that neither program halts on within `max_steps` are considered equal.
+/// Both programs must have one tape.
+pub fn behaviour_difference(a: &Program, b: &Program, inputs: impl IntoIterator<Item=TestCase>, max_steps: u64) -> Option<BehaviourDifference> {
+    inputs.into_iter().find_map(|tc| {
+        let input = sorted(&tc.initial_tape);
+        let ending_a = ending(a, tc.clone(), max_steps);
+        let ending_b = ending(b, tc, max_steps);
+        if ending_a != ending_b {
+            Some(BehaviourDifference { input, a: ending_a, b: ending_b })
+        } else {
+            None
+        }
+    })
+}
+
+fn ending(program: &Program, tc: TestCase, max_steps: u64) -> Ending {
+    let mut execution = MacroExecution::new(tc, program.clone());
+    if !execution.run(max_steps) {
+        Ending::Running
+    } else if execution.fell_off() {
+        Ending::FellOff { position: execution.get_current_position() }
+    } else {
+        Ending::Halted {
+            position: execution.get_current_position(),
+            tape: sorted(&execution.get_tape()),
+        }
+    }
+}
+
+pub fn sorted(tape: &HashSet<i64>) -> Vec<i64> {
+    let mut tape: Vec<i64> = tape.iter().copied().collect();
+    tape.sort();
+    tape
+}
+
+pub struct StructuralDiff {
+    /// Pairs of card names that correspond to each other, starting with the initial cards
+    pub matched: Vec<(String, String)>,
+    pub differences: Vec<String>,
+}
+
+impl StructuralDiff {
+    pub fn is_isomorphic(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// Matches the cards of both programs up to renaming, by following the instructions from the
+/// initial cards of both programs in lockstep.
+pub fn structural_diff(a: &Program, b: &Program) -> StructuralDiff {
+    let mut a_to_b: HashMap<usize, usize> = HashMap::new();
+    let mut b_to_a: HashMap<usize, usize> = HashMap::new();
+    let mut matched = Vec::new();
+    let mut differences = Vec::new();
+    let mut queue = VecDeque::from([(a.initial_card, b.initial_card)]);
+    a_to_b.insert(a.initial_card, b.initial_card);
+    b_to_a.insert(b.initial_card, a.initial_card);
+    if a.tapes() != b.tapes() {
+        differences.push(format!("{} has {} tapes and {} has {}", a.name, a.tapes(), b.name, b.tapes()));
+        return StructuralDiff { matched, differences };
+    }
+    let tapes = a.tapes();
+
+    while let Some((index_a, index_b)) = queue.pop_front() {
+        let card_a = &a.cards[index_a];
+        let card_b = &b.cards[index_b];
+        matched.push((card_a.name.clone(), card_b.name.clone()));
+        // Instructions for lit cells first, so cards are matched in the same order as always
+        let instructions = card_a.instructions().zip(card_b.instructions()).enumerate();
+        let mut pairs: Vec<(usize, &Instruction, &Instruction, &str)> = instructions
+            .map(|(read, (instruction_a, instruction_b))| (read, instruction_a, instruction_b, ""))
+            .collect();
+        pairs.reverse();
+        if card_a.alternatives.len() != card_b.alternatives.len() {
+            differences.push(format!("{} / {}: has {} / {} alternatives", card_a.name, card_b.name, card_a.alternatives.len(), card_b.alternatives.len()));
+        }
+        for (alternative_a, alternative_b) in card_a.alternatives.iter().zip(&card_b.alternatives) {
+            if alternative_a.read != alternative_b.read {
+                differences.push(format!("{} / {}: has an alternative for different symbols", card_a.name, card_b.name));
+                continue;
+            }
+            pairs.push((alternative_a.read, &alternative_a.instruction, &alternative_b.instruction, " alternative"));
+        }
+        for (read, instruction_a, instruction_b, kind) in pairs {
+            let reading = if tapes == 1 {
+                format!("with the tape {}", symbol(read == 1))
+            } else {
+                format!("reading {}", (0..tapes).map(|tape| symbol(read >> tape & 1 == 1)).collect::<Vec<_>>().join(", "))
+            };
+            let describe = |message: String| format!("{} / {}{} {}: {}", card_a.name, card_b.name, kind, reading, message);
+            for (tape, (action_a, action_b)) in actions(instruction_a).into_iter().zip(actions(instruction_b)).enumerate() {
+                let on_tape = if tapes == 1 { String::new() } else { format!(" on tape {}", tape + 1) };
+                let on = read >> tape & 1 == 1;
+                let write_a = action_a.write_symbol.unwrap_or(on);
+                let write_b = action_b.write_symbol.unwrap_or(on);
+                if write_a != write_b {
+                    differences.push(describe(format!("writes {} / {}{}", symbol(write_a), symbol(write_b), on_tape)));
+                }
+                if !same_direction(&action_a, &action_b) {
+                    differences.push(describe(format!("moves {} / {}{}", direction(&action_a), direction(&action_b), on_tape)));
+                }
+            }
+            match (instruction_a.next_card, instruction_b.next_card) {
+                (None, None) => {}
+                (Some(next_a), Some(next_b)) => match (a_to_b.get(&next_a), b_to_a.get(&next_b)) {
+                    (None, None) => {
+                        a_to_b.insert(next_a, next_b);
+                        b_to_a.insert(next_b, next_a);
+                        queue.push_back((next_a, next_b));
+                    }
+                    (Some(&matched_b), _) if matched_b == next_b => {}
+                    _ => differences.push(describe(format!("continues with {} / {}", a.cards[next_a].name, b.cards[next_b].name))),
+                },
+                (next_a, next_b) => differences.push(describe(format!("continues with {} / {}", next_name(a, next_a), next_name(b, next_b)))),
+            }
+        }
+    }
+
+    for (program, matched_cards) in [(a, &a_to_b), (b, &b_to_a)] {
+        for (index, card) in program.cards.iter().enumerate() {
+            if !matched_cards.contains_key(&index) {
+                differences.push(format!("Card {} of {} has no counterpart", card.name, program.name));
+            }
+        }
+    }
+
+    StructuralDiff { matched, differences }
+}
+
+fn symbol(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+/// What the instruction does to every tape, starting with the first one
+fn actions(instruction: &Instruction) -> Vec<TapeAction> {
+    let first = TapeAction {
+        write_symbol: instruction.write_symbol,
+        move_direction: instruction.move_direction,
+    };
+    iter::once(first).chain(instruction.other_tapes.iter().cloned()).collect()
+}
+
+fn same_direction(a: &TapeAction, b: &TapeAction) -> bool {
+    a.move_direction == b.move_direction
+}
+
+fn direction(action: &TapeAction) -> &'static str {
+    match action.move_direction {
+        None => "nowhere",
+        Some(Direction::Left) => "left",
+        Some(Direction::Right) => "right",
+        Some(Direction::Up) => "up",
+        Some(Direction::Down) => "down",
+    }
+}
+
+fn next_name(program: &Program, next_card: Option<usize>) -> String {
+    next_card.map(|index| program.cards[index].name.clone()).unwrap_or("halt".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::equivalence::{behaviour_difference, structural_diff, Ending};
+    use crate::level::TestCase;
+    use crate::program::Program;
+    use crate::programs;
+
+    fn builtin(name: &str) -> Program {
+        programs::builtins().into_iter().find(|program| program.name == name).unwrap()
+    }
+
+    fn inputs() -> Vec<TestCase> {
+        [vec![], vec![0], vec![2], vec![-1, 3]].into_iter()
+            .map(|tape| TestCase {
+                initial_tape: tape.into_iter().collect(),
+                ..TestCase::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renamed_and_reordered_cards_are_isomorphic() {
+        let a = builtin("addition_solution");
+        let mut b = a.clone();
+        b.cards.reverse();
+        for card in b.cards.iter_mut() {
+            card.name = card.name.to_lowercase();
+            for instruction in [&mut card.tape_on, &mut card.tape_off] {
+                instruction.next_card = instruction.next_card.map(|index| 2 - index);
+            }
+        }
+        b.initial_card = 2;
+
+        let diff = structural_diff(&a, &b);
+        assert!(diff.is_isomorphic(), "{:?}", diff.differences);
+        assert_eq!(diff.matched[0], ("First".to_string(), "first".to_string()));
+        assert!(behaviour_difference(&a, &b, inputs(), 1000).is_none());
+    }
+
+    #[test]
+    fn different_writes() {
+        let a = builtin("night_time_solution");
+        let mut b = a.clone();
+        b.cards[0].tape_on.write_symbol = None;
+
+        let diff = structural_diff(&a, &b);
+        assert_eq!(diff.differences, vec!["Search / Search with the tape on: writes off / on"]);
+
+        let difference = behaviour_difference(&a, &b, inputs(), 1000).unwrap();
+        assert_eq!(difference.input, vec![0]);
+        assert_eq!(difference.a, Ending::Halted { position: 0, tape: vec![] });
+        assert_eq!(difference.b, Ending::Halted { position: 0, tape: vec![0] });
+    }
+
+    #[test]
+    fn halting_and_running() {
+        let difference = behaviour_difference(&builtin("just_stop"), &builtin("go_right"), inputs(), 1000).unwrap();
+        assert_eq!(difference.input, Vec::<i64>::new());
+        assert_eq!(difference.b, Ending::Running);
+        assert!(!structural_diff(&builtin("just_stop"), &builtin("go_right")).is_isomorphic());
+    }
+}
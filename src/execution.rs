@@ -1,10 +1,13 @@
 use std::collections::HashSet;
+use std::hash::Hash;
+use std::ops::ControlFlow;
 use std::fmt::{Display, Formatter, write};
 
-use Direction::{Left, Right};
+use Direction::{Down, Left, Right, Up};
 
 use crate::execution::TestCaseExecutionState::{Failure, Pending, Running, Success};
-use crate::level::{Level, Target, TestCase};
+use crate::level::{Level, Target, TestCase, Topology};
+use crate::parallel;
 use crate::program::{Card, Direction, Program};
 
 pub struct LevelExecution {
@@ -24,12 +27,43 @@ impl LevelExecution {
     }
 
     pub fn step(&mut self) {
-        if self.is_terminated() {
-            return;
+        let _ = self.step_observed(&mut ());
+    }
+
+    /// Steps the current test case, and tells whether the observer asked to stop there
+    pub fn step_observed(&mut self, observer: &mut dyn ExecutionObserver) -> ControlFlow<()> {
+        match self.current_execution_mut() {
+            Some(ex) => ex.step_observed(observer),
+            None => ControlFlow::Continue(()),
         }
-        if let Some(ex) = self.current_execution_mut() {
-            ex.step()
+    }
+
+    /// Runs every test case to completion, giving each at most `max_steps` steps.
+    /// Returns whether all test cases terminated.
+    pub fn run(&mut self, max_steps: u64) -> bool {
+        self.run_observed(max_steps, &mut ())
+    }
+
+    /// Like `run`, but stops right after a step for which the observer returns `Break`, so that
+    /// the test cases that are left can be run later.
+    pub fn run_observed(&mut self, max_steps: u64, observer: &mut dyn ExecutionObserver) -> bool {
+        for tce in self.executions.iter_mut().filter(|tce| !tce.is_terminated()) {
+            if tce.run_until(max_steps, observer).is_break() {
+                break;
+            }
         }
+        self.executions.iter().all(TestCaseExecution::is_terminated)
+    }
+
+    /// Like `run`, but runs the test cases on up to `jobs` threads.
+    pub fn run_parallel(&mut self, max_steps: u64, jobs: usize) -> bool {
+        let pending = self.executions.iter_mut().filter(|tce| !tce.is_terminated()).collect();
+        parallel::map(pending, jobs, |tce| tce.run(max_steps));
+        self.executions.iter().all(TestCaseExecution::is_terminated)
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.executions.iter().all(|tce| matches!(tce.get_state(), Success))
     }
 
     pub fn current_execution_mut(&mut self) -> Option<&mut TestCaseExecution> {
@@ -52,59 +86,230 @@ impl LevelExecution {
     }
 }
 
+#[derive(Clone)]
 pub struct TestCaseExecution {
     pub positions_on: HashSet<i64>,
     current_card_index: Option<usize>,
     pub current_position: i64,
+    /// The first tape, instead of `positions_on`, for test cases on a grid
+    pub grid: Option<Grid>,
+    pub other_tapes: Vec<OtherTape>,
     pub steps: u64,
     program: Program,
     target: Option<Target>,
+    topology: Topology,
+    /// Whether the first head moved past the end of its tape, which halts the execution
+    fell_off: bool,
+}
+
+/// A tape other than the first one, for programs or levels with more than one tape.
+#[derive(Clone, Default)]
+pub struct OtherTape {
+    pub positions_on: HashSet<i64>,
+    pub current_position: i64,
+    target: Option<Target>,
+}
+
+impl OtherTape {
+    fn is_on(&self) -> bool {
+        self.positions_on.contains(&self.current_position)
+    }
+}
+
+/// The first tape of a test case, when it is a grid.
+#[derive(Clone, Default)]
+pub struct Grid {
+    pub cells_on: HashSet<(i64, i64)>,
+    /// Position of the head as `(x, y)`. The `x` is also the `current_position` of the execution.
+    pub position: (i64, i64),
+}
+
+impl Grid {
+    fn is_on(&self) -> bool {
+        self.cells_on.contains(&self.position)
+    }
+
+    fn apply(&mut self, write_symbol: Option<bool>, move_direction: Option<Direction>) {
+        write_cell(&mut self.cells_on, self.position, write_symbol);
+        let (x, y) = &mut self.position;
+        match move_direction {
+            Some(Left) => *x -= 1,
+            Some(Right) => *x += 1,
+            Some(Up) => *y -= 1,
+            Some(Down) => *y += 1,
+            None => {}
+        }
+    }
+}
+
+fn apply(positions_on: &mut HashSet<i64>, position: &mut i64, write_symbol: Option<bool>, move_direction: Option<Direction>) {
+    write_cell(positions_on, *position, write_symbol);
+    match move_direction {
+        Some(Left) => *position -= 1,
+        Some(Right) => *position += 1,
+        // Up and down leave the head where it is on a line
+        Some(Up) | Some(Down) | None => {}
+    }
+}
+
+fn write_cell<T: Eq + Hash>(cells_on: &mut HashSet<T>, cell: T, write_symbol: Option<bool>) {
+    match write_symbol {
+        Some(true) => {
+            cells_on.insert(cell);
+        }
+        Some(false) => {
+            cells_on.remove(&cell);
+        }
+        None => {}
+    }
 }
 
 impl TestCaseExecution {
     pub fn new(tc: TestCase, program: Program) -> TestCaseExecution {
+        let tapes = program.tapes().max(1 + tc.other_tapes.len());
+        let mut other_tapes: Vec<OtherTape> = tc.other_tapes.into_iter()
+            .map(|tape| OtherTape {
+                positions_on: tape.initial_tape,
+                current_position: 0,
+                target: tape.target,
+            })
+            .collect();
+        other_tapes.resize(tapes - 1, OtherTape::default());
+        let grid = match tc.topology {
+            Topology::Grid => Some(Grid {
+                cells_on: tc.initial_grid,
+                position: (0, 0),
+            }),
+            _ => None,
+        };
         TestCaseExecution {
             positions_on: tc.initial_tape,
-            current_card_index: Some(0),
+            grid,
+            current_card_index: Some(program.initial_card),
             current_position: 0,
+            other_tapes,
             steps: 0,
             program,
             target: tc.target,
+            topology: tc.topology,
+            fell_off: false,
         }
     }
 
     pub fn step(&mut self) {
+        let _ = self.step_observed(&mut ());
+    }
+
+    /// Steps, and tells whether the observer asked to stop after this step
+    pub fn step_observed(&mut self, observer: &mut dyn ExecutionObserver) -> ControlFlow<()> {
+        self.step_choice_observed(0, observer)
+    }
+
+    /// Steps with the instruction at `choice` of the choices of the current card for what the
+    /// heads read, see `Card::choices`. Only nondeterministic programs have more than one.
+    pub fn step_choice(&mut self, choice: usize) {
+        let _ = self.step_choice_observed(choice, &mut ());
+    }
+
+    pub fn step_choice_observed(&mut self, choice: usize, observer: &mut dyn ExecutionObserver) -> ControlFlow<()> {
+        let mut flow = ControlFlow::Continue(());
         if let Some(index) = self.current_card_index {
             let card = self.program.cards.get(index).unwrap();
-            let current_position = &self.current_position;
-            let on = self.positions_on.contains(current_position);
-            let instruction = if on { &card.tape_on } else { &card.tape_off };
-            if let Some(symbol) = instruction.write_symbol {
-                if symbol {
-                    self.positions_on.insert(*current_position);
-                } else {
-                    self.positions_on.remove(current_position);
+            let from = self.get_current_cell();
+            let on = self.is_on();
+            let read = self.get_read();
+            let instruction = card.choices(read).nth(choice).unwrap();
+            let instruction_index = card.instruction_index(read, choice);
+            match &mut self.grid {
+                Some(grid) => {
+                    grid.apply(instruction.write_symbol, instruction.move_direction);
+                    self.current_position = grid.position.0;
                 }
+                None => {
+                    apply(&mut self.positions_on, &mut self.current_position, instruction.write_symbol, instruction.move_direction);
+                    match self.topology.place(self.current_position) {
+                        Some(position) => self.current_position = position,
+                        None => self.fell_off = true,
+                    }
+                }
+            }
+            if let Some(symbol) = instruction.write_symbol {
+                observer.on_write(0, from, symbol);
             }
-            if let Some(move_direction) = instruction.move_direction {
-                match move_direction {
-                    Left => self.current_position -= 1,
-                    Right => self.current_position += 1,
+            for (tape, (other, action)) in self.other_tapes.iter_mut().zip(&instruction.other_tapes).enumerate() {
+                if let Some(symbol) = action.write_symbol {
+                    observer.on_write(tape + 1, Cell::Line(other.current_position), symbol);
                 }
+                apply(&mut other.positions_on, &mut other.current_position, action.write_symbol, action.move_direction);
             }
-            self.current_card_index = instruction.next_card;
+            self.current_card_index = if self.fell_off { None } else { instruction.next_card };
             self.steps += 1;
+
+            flow = observer.on_step(&Step {
+                card: index,
+                on,
+                read,
+                instruction: instruction_index,
+                from,
+                to: self.get_current_cell(),
+                next_card: self.current_card_index,
+            });
+            match self.current_card_index {
+                Some(next) if next != index => observer.on_card_change(index, next),
+                Some(_) => {}
+                None => {
+                    observer.on_halt(index, on, self.steps);
+                    observer.on_case_complete(&self.get_state());
+                }
+            }
+        }
+        flow
+    }
+
+    /// Whether the first head is on a lit cell
+    fn is_on(&self) -> bool {
+        match &self.grid {
+            Some(grid) => grid.is_on(),
+            None => self.positions_on.contains(&self.current_position),
+        }
+    }
+
+    /// What the heads read, as given to `Card::instruction`
+    pub fn get_read(&self) -> usize {
+        // Tapes the program does not know about are never read
+        self.other_tapes.iter()
+            .take(self.program.tapes() - 1)
+            .enumerate()
+            .fold(self.is_on() as usize, |read, (tape, other)| read | (other.is_on() as usize) << (tape + 1))
+    }
+
+    /// Amount of instructions the current card can choose from, which is 0 once terminated
+    pub fn get_choice_count(&self) -> usize {
+        match self.current_card_index {
+            Some(index) => self.program.cards[index].choices(self.get_read()).count(),
+            None => 0,
         }
     }
 
     pub fn run(&mut self, max_steps: u64) -> bool {
+        self.run_observed(max_steps, &mut ())
+    }
+
+    /// Like `run`, but also stops right after a step for which the observer returns `Break`
+    pub fn run_observed(&mut self, max_steps: u64, observer: &mut dyn ExecutionObserver) -> bool {
+        let _ = self.run_until(max_steps, observer);
+        self.is_terminated()
+    }
+
+    /// Runs until termination, for at most `max_steps` steps, or until the observer stops it
+    fn run_until(&mut self, max_steps: u64, observer: &mut dyn ExecutionObserver) -> ControlFlow<()> {
         for _n in 0..max_steps {
-            self.step();
+            self.step_observed(observer)?;
             if self.is_terminated() {
-                return true;
+                break;
             }
         }
-        false
+        ControlFlow::Continue(())
     }
 
     pub fn is_terminated(&self) -> bool {
@@ -115,6 +320,14 @@ impl TestCaseExecution {
         self.current_position
     }
 
+    /// The cell of the head on the first tape
+    pub fn get_current_cell(&self) -> Cell {
+        match &self.grid {
+            Some(grid) => Cell::Grid(grid.position.0, grid.position.1),
+            None => Cell::Line(self.current_position),
+        }
+    }
+
     pub fn get_tape_at(&self, position: i64) -> bool {
         self.positions_on.contains(&position)
     }
@@ -135,21 +348,59 @@ impl TestCaseExecution {
     }
 
     fn get_errors(&self) -> Vec<String> {
-        match &self.target {
-            None => vec![],
-            Some(target) => match target {
-                Target::TapeExact { tape } => if &self.positions_on != tape {
-                    vec![format!("Wrong final tape")]
-                } else {
-                    vec![]
-                }
-                Target::Position { position } => if &self.current_position != position {
-                    vec![format!("Wrong final position: wanted {} but was {}", position, self.current_position)]
-                } else {
-                    vec![]
-                }
+        if self.fell_off {
+            return vec![format!("Fell off the tape at position {}", self.current_position)];
+        }
+        let mut errors = match (&self.target, &self.grid) {
+            (None, _) => vec![],
+            (Some(target), Some(grid)) => target.get_grid_errors(&grid.cells_on, grid.position),
+            (Some(target), None) => target.get_errors(&self.positions_on, self.current_position),
+        };
+        for (tape, other) in self.other_tapes.iter().enumerate() {
+            if let Some(target) = &other.target {
+                let tape_errors = target.get_errors(&other.positions_on, other.current_position);
+                errors.extend(tape_errors.into_iter().map(|error| format!("Tape {}: {}", tape + 2, error)));
             }
         }
+        errors
+    }
+
+    /// Shape of the first tape
+    pub fn get_topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// The first tape, when the test case is on a grid
+    pub fn get_grid(&self) -> Option<&Grid> {
+        self.grid.as_ref()
+    }
+
+    /// Amount of tapes, which is the most tapes of the program and the test case
+    pub fn get_tape_count(&self) -> usize {
+        1 + self.other_tapes.len()
+    }
+
+    /// Position of the head of a tape, where the first tape is 0
+    pub fn get_current_position_on(&self, tape: usize) -> i64 {
+        match tape {
+            0 => self.current_position,
+            _ => self.other_tapes[tape - 1].current_position,
+        }
+    }
+
+    pub fn get_tape_at_on(&self, tape: usize, position: i64) -> bool {
+        match tape {
+            0 => self.positions_on.contains(&position),
+            _ => self.other_tapes[tape - 1].positions_on.contains(&position),
+        }
+    }
+
+    pub fn get_program_mut(&mut self) -> &mut Program {
+        &mut self.program
+    }
+
+    pub fn get_current_card_index(&self) -> Option<usize> {
+        self.current_card_index
     }
 
     pub fn get_current_card(&self) -> Option<&Card> {
@@ -157,6 +408,135 @@ impl TestCaseExecution {
     }
 }
 
+/// A cell of a tape, which has a row as well on a grid.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Cell {
+    /// The position of a cell on a line
+    Line(i64),
+    /// The `(x, y)` of a cell on a grid
+    Grid(i64, i64),
+}
+
+impl Cell {
+    /// Position from left to right, which is the column on a grid
+    pub fn x(&self) -> i64 {
+        match *self {
+            Cell::Line(x) | Cell::Grid(x, _) => x,
+        }
+    }
+
+    /// Amount of moves a head needs to get from one cell to the other
+    pub fn distance(&self, other: &Cell) -> u64 {
+        match (*self, *other) {
+            (Cell::Grid(x, y), Cell::Grid(other_x, other_y)) => x.abs_diff(other_x) + y.abs_diff(other_y),
+            _ => self.x().abs_diff(other.x()),
+        }
+    }
+}
+
+impl Display for Cell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Line(x) => write!(f, "{}", x),
+            Cell::Grid(x, y) => write!(f, "({}, {})", x, y),
+        }
+    }
+}
+
+/// What a single step of a `TestCaseExecution` did.
+pub struct Step {
+    /// Index of the card whose instruction was executed
+    pub card: usize,
+    /// Whether the cell under the head was lit before the step
+    pub on: bool,
+    /// What all heads read before the step, as in `Card::instruction`
+    pub read: usize,
+    /// Index of the executed instruction in `Card::all_instructions`
+    pub instruction: usize,
+    /// Cell of the head on the first tape before the step
+    pub from: Cell,
+    /// Cell of the head on the first tape after the step
+    pub to: Cell,
+    pub next_card: Option<usize>,
+}
+
+/// Receives the events of an execution as it happens, so renderers, tracers, statistics and
+/// breakpoints do not have to inspect the execution after every step.
+///
+/// All methods do nothing by default. Within a step, writes are reported first, then the step
+/// itself, then either the card change or the halt and the completion of the test case.
+pub trait ExecutionObserver {
+    /// Returning `Break` stops `run_observed` once the step is complete, which is how breakpoints
+    /// pause a run.
+    fn on_step(&mut self, _step: &Step) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// The next step will execute a different card than this one did.
+    fn on_card_change(&mut self, _from: usize, _to: usize) {}
+
+    /// A symbol was written to a cell of a tape, where the first tape is 0.
+    fn on_write(&mut self, _tape: usize, _cell: Cell, _on: bool) {}
+
+    /// The instruction of `card` for a tape that is `on` halted the program after `steps` steps.
+    fn on_halt(&mut self, _card: usize, _on: bool, _steps: u64) {}
+
+    fn on_case_complete(&mut self, _state: &TestCaseExecutionState) {}
+}
+
+impl ExecutionObserver for () {}
+
+impl<T: ExecutionObserver + ?Sized> ExecutionObserver for &mut T {
+    fn on_step(&mut self, step: &Step) -> ControlFlow<()> {
+        (**self).on_step(step)
+    }
+
+    fn on_card_change(&mut self, from: usize, to: usize) {
+        (**self).on_card_change(from, to)
+    }
+
+    fn on_write(&mut self, tape: usize, cell: Cell, on: bool) {
+        (**self).on_write(tape, cell, on)
+    }
+
+    fn on_halt(&mut self, card: usize, on: bool, steps: u64) {
+        (**self).on_halt(card, on, steps)
+    }
+
+    fn on_case_complete(&mut self, state: &TestCaseExecutionState) {
+        (**self).on_case_complete(state)
+    }
+}
+
+/// Tells both observers of every event, and stops when either of them asks to
+impl<A: ExecutionObserver, B: ExecutionObserver> ExecutionObserver for (A, B) {
+    fn on_step(&mut self, step: &Step) -> ControlFlow<()> {
+        let first = self.0.on_step(step);
+        let second = self.1.on_step(step);
+        if first.is_break() { first } else { second }
+    }
+
+    fn on_card_change(&mut self, from: usize, to: usize) {
+        self.0.on_card_change(from, to);
+        self.1.on_card_change(from, to);
+    }
+
+    fn on_write(&mut self, tape: usize, cell: Cell, on: bool) {
+        self.0.on_write(tape, cell, on);
+        self.1.on_write(tape, cell, on);
+    }
+
+    fn on_halt(&mut self, card: usize, on: bool, steps: u64) {
+        self.0.on_halt(card, on, steps);
+        self.1.on_halt(card, on, steps);
+    }
+
+    fn on_case_complete(&mut self, state: &TestCaseExecutionState) {
+        self.0.on_case_complete(state);
+        self.1.on_case_complete(state);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum TestCaseExecutionState {
     Pending,
@@ -167,29 +547,43 @@ pub enum TestCaseExecutionState {
 
 #[cfg(test)]
 mod tests {
-    use crate::execution::TestCaseExecution;
+    use std::collections::HashSet;
+    use std::ops::ControlFlow;
+
+    use crate::execution::{Cell, ExecutionObserver, LevelExecution, Step, TestCaseExecution, TestCaseExecutionState};
+    use crate::level::{Level, TestCase, Topology};
+    use crate::levels;
     use crate::program::{Card, Instruction, Program};
     use crate::program::Direction::Right;
+    use crate::programs;
+
+    fn find_level(name: &str) -> Level {
+        levels::builtins().into_iter().find(|level| level.name == name).unwrap()
+    }
 
     #[test]
     fn test_just_stop() {
-        let level = sandbox();
+        let level = find_level("sandbox");
         let terminate = Instruction {
             write_symbol: Some(false),
             move_direction: Some(Right),
             next_card: None,
+            other_tapes: Vec::new(),
         };
         let card = Card {
             name: "Terminate".into(),
             tape_on: terminate.clone(),
             tape_off: terminate,
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
         };
         let program = Program {
             name: String::from("It stops"),
+            description: String::new(),
             initial_card: 0,
             cards: vec![card],
         };
-        let mut engine = TestCaseExecution::new(level.cases[0].initial_tape.clone(), program);
+        let mut engine = TestCaseExecution::new(level.cases[0].clone(), program);
         assert!(!engine.is_terminated());
         engine.step();
         assert!(engine.is_terminated());
@@ -197,9 +591,10 @@ mod tests {
 
     #[test]
     fn night_time_solve() {
-        let level = night_time();
+        let level = find_level("night_time");
         let program = Program {
             name: "".to_string(),
+            description: String::new(),
             initial_card: 0,
             cards: vec![Card {
                 name: "".to_string(),
@@ -207,16 +602,149 @@ mod tests {
                     write_symbol: Some(false),
                     move_direction: Some(Right),
                     next_card: None,
+                    other_tapes: Vec::new(),
                 },
                 tape_off: Instruction {
                     write_symbol: Some(false),
                     move_direction: Some(Right),
                     next_card: Some(0),
+                    other_tapes: Vec::new(),
                 },
+                other_instructions: Vec::new(),
+                alternatives: Vec::new(),
             }],
         };
         let mut engine = TestCaseExecution::new(level.cases[0].clone(), program);
         let terminated = engine.run(100);
         assert!(terminated);
     }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl ExecutionObserver for Recorder {
+        fn on_step(&mut self, step: &Step) -> ControlFlow<()> {
+            self.events.push(format!("step {} {} -> {}", step.card, step.from, step.to));
+            ControlFlow::Continue(())
+        }
+
+        fn on_card_change(&mut self, from: usize, to: usize) {
+            self.events.push(format!("card {} -> {}", from, to));
+        }
+
+        fn on_write(&mut self, tape: usize, cell: Cell, on: bool) {
+            assert_eq!(tape, 0);
+            self.events.push(format!("write {} {}", cell, on));
+        }
+
+        fn on_halt(&mut self, card: usize, on: bool, steps: u64) {
+            self.events.push(format!("halt {} {} {}", card, on, steps));
+        }
+
+        fn on_case_complete(&mut self, state: &TestCaseExecutionState) {
+            self.events.push(format!("complete {:?}", state));
+        }
+    }
+
+    #[test]
+    fn observer_sees_every_event() {
+        let level = find_level("increment");
+        let program = programs::builtins().into_iter().find(|program| program.name == "increment_solution").unwrap();
+        let mut execution = LevelExecution::new(level, program);
+        let mut recorder = Recorder::default();
+        execution.run_observed(100, &mut recorder);
+        assert!(execution.is_solved());
+        assert_eq!(recorder.events[..4], ["write 0 true", "step 0 0 -> 0", "halt 0 false 1", "complete Success"]);
+
+        let completed = recorder.events.iter().filter(|event| event.starts_with("complete")).count();
+        assert_eq!(completed, execution.executions.len());
+        assert!(recorder.events.iter().filter(|event| event.starts_with("complete")).all(|event| event == "complete Success"));
+        let steps = recorder.events.iter().filter(|event| event.starts_with("step")).count() as u64;
+        assert_eq!(steps, execution.get_steps());
+    }
+
+    #[test]
+    fn observer_sees_rows_of_grids() {
+        let level = find_level("box");
+        let program = programs::builtins().into_iter().find(|program| program.name == "box_solution").unwrap();
+        let mut execution = LevelExecution::new(level, program);
+        let mut recorder = Recorder::default();
+        execution.run_observed(100, &mut recorder);
+        assert!(execution.is_solved());
+        assert!(recorder.events.iter().any(|event| event.starts_with("step") && event.ends_with("-> (0, 1)")), "{:#?}", recorder.events);
+        assert!(recorder.events.iter().any(|event| event == "write (1, 1) true"), "{:#?}", recorder.events);
+    }
+
+    #[test]
+    fn parallel_run_is_the_same() {
+        let level = find_level("moth");
+        let program = programs::builtins().into_iter().find(|program| program.name == "moth_solution").unwrap();
+        let mut sequential = LevelExecution::new(level.clone(), program.clone());
+        let mut parallel = LevelExecution::new(level, program);
+        assert!(sequential.run(10_000));
+        assert!(parallel.run_parallel(10_000, 4));
+        assert!(parallel.is_solved());
+        let steps = |execution: &LevelExecution| execution.executions.iter().map(|tce| tce.steps).collect::<Vec<_>>();
+        assert_eq!(steps(&parallel), steps(&sequential));
+    }
+
+    #[test]
+    fn other_tapes_are_checked() {
+        let level = find_level("copy");
+        let mut program = programs::builtins().into_iter().find(|program| program.name == "copy_solution").unwrap();
+        program.cards[0].tape_on.other_tapes[0].write_symbol = None;
+        let mut execution = LevelExecution::new(level, program);
+        execution.run(100);
+        let tce = &execution.executions[1];
+        assert_eq!(tce.get_tape_count(), 2);
+        assert_eq!(tce.get_current_position_on(1), 1);
+        assert!(matches!(tce.get_state(), TestCaseExecutionState::Failure { errors } if errors == ["Tape 2: Wrong final tape"]));
+        assert!(matches!(execution.executions[0].get_state(), TestCaseExecutionState::Success));
+    }
+
+    #[test]
+    fn langtons_ant_walks_a_grid() {
+        let program = programs::builtins().into_iter().find(|program| program.name == "langtons_ant").unwrap();
+        let test_case = TestCase { topology: Topology::Grid, ..TestCase::default() };
+        let mut execution = TestCaseExecution::new(test_case, program);
+        for _ in 0..4 {
+            execution.step();
+        }
+        let grid = execution.get_grid().unwrap();
+        assert_eq!(grid.cells_on, HashSet::from([(0, 0), (1, 0), (1, 1), (0, 1)]));
+        assert_eq!(grid.position, (0, 0));
+        assert_eq!(execution.get_current_position(), 0);
+        assert_eq!(execution.get_current_card().map(|card| card.name.as_str()), Some("North"));
+    }
+
+    #[test]
+    fn heads_stay_on_their_tape() {
+        let program = Program {
+            name: "left".to_string(),
+            description: String::new(),
+            initial_card: 0,
+            cards: vec![Card::go_left()],
+        };
+        let run = |topology: Topology| {
+            let mut execution = TestCaseExecution::new(TestCase { topology, ..TestCase::default() }, program.clone());
+            execution.run(3);
+            execution
+        };
+
+        let stays = run(Topology::SemiInfinite { fall_off: false });
+        assert_eq!((stays.get_current_position(), stays.steps), (0, 3));
+        assert!(!stays.is_terminated());
+
+        let circular = run(Topology::Circular { length: 5 });
+        assert_eq!(circular.get_current_position(), 2);
+
+        for topology in [Topology::SemiInfinite { fall_off: true }, Topology::Bounded { from: -2, to: 2 }] {
+            let falls = run(topology);
+            assert!(falls.is_terminated());
+            assert!(matches!(falls.get_state(), TestCaseExecutionState::Failure { errors } if errors[0].starts_with("Fell off the tape")));
+        }
+        assert_eq!(run(Topology::Bounded { from: -2, to: 2 }).steps, 3);
+    }
 }
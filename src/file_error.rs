@@ -0,0 +1,323 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// A line and a column in a file, counted from 1
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// What an error is about, to find where it is in the text of the file it came from
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Subject {
+    /// Nothing in particular, or a place the error already has a location for
+    File,
+    /// A field at the top of a program, by its key in yaml
+    Field(&'static str),
+    /// The definition of a card
+    Card(String),
+    /// A card that another card continues with
+    Reference { card: String, name: String },
+}
+
+/// A mistake in a program or level file
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FileError {
+    pub location: Option<Location>,
+    pub subject: Subject,
+    pub message: String,
+    /// A name that was probably meant instead of a misspelled one
+    pub suggestion: Option<String>,
+}
+
+impl FileError {
+    pub fn new(subject: Subject, message: impl Into<String>) -> FileError {
+        FileError { location: None, subject, message: message.into(), suggestion: None }
+    }
+
+    pub fn at(line: usize, column: usize, message: impl Into<String>) -> FileError {
+        FileError { location: Some(Location { line, column }), subject: Subject::File, message: message.into(), suggestion: None }
+    }
+
+    /// Suggests the candidate closest to a misspelled name, if any is close enough
+    pub fn suggest<'a>(self, name: &str, candidates: impl IntoIterator<Item=&'a str>) -> FileError {
+        FileError { suggestion: suggest(name, candidates), ..self }
+    }
+
+    /// The message with the suggestion, without the location
+    fn describe(&self) -> String {
+        match &self.suggestion {
+            Some(suggestion) => format!("{}. Did you mean {}?", self.message, suggestion),
+            None => self.message.clone(),
+        }
+    }
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.location {
+            Some(Location { line, column }) => write!(f, "line {}, column {}: {}", line, column, self.describe()),
+            None => write!(f, "{}", self.describe()),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+/// Every mistake found in a file, in the order they appear in it
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FileErrors {
+    pub path: Option<PathBuf>,
+    pub errors: Vec<FileError>,
+}
+
+impl FileErrors {
+    /// Gives the errors the path of the file they are in, and finds where the ones without a
+    /// location are in its text, which is yaml unless the path has a `.tur` extension.
+    pub fn locate(mut self, path: &Path, text: &str) -> FileErrors {
+        let lines: Vec<&str> = text.lines().collect();
+        let is_text_format = path.extension().is_some_and(|extension| extension == "tur");
+        for error in &mut self.errors {
+            if error.location.is_none() {
+                error.location = if is_text_format {
+                    locate_in_text(&lines, &error.subject)
+                } else {
+                    locate_in_yaml(&lines, &error.subject)
+                };
+            }
+        }
+        self.errors.sort_by_key(|error| error.location.map(|location| (location.line, location.column)));
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl From<FileError> for FileErrors {
+    fn from(error: FileError) -> Self {
+        FileErrors { path: None, errors: vec![error] }
+    }
+}
+
+impl From<Vec<FileError>> for FileErrors {
+    fn from(errors: Vec<FileError>) -> Self {
+        FileErrors { path: None, errors }
+    }
+}
+
+/// Takes the location out of the message of a yaml error, and suggests the closest of the
+/// variants or fields that serde expected instead of an unknown one.
+impl From<serde_yaml::Error> for FileErrors {
+    fn from(error: serde_yaml::Error) -> Self {
+        let mut message = error.to_string();
+        let location = error.location().map(|location| Location { line: location.line(), column: location.column() });
+        if location.is_some() {
+            if let Some(index) = message.rfind(" at line ") {
+                message.truncate(index);
+            }
+        }
+        let mut suggestion = None;
+        if let Some((unknown, expected)) = message.split_once(", expected ") {
+            if let (true, Some(name)) = (unknown.contains("unknown "), unknown.split('`').nth(1)) {
+                suggestion = suggest(name, expected.split('`').skip(1).step_by(2));
+            }
+        }
+        FileError { location, subject: Subject::File, message, suggestion }.into()
+    }
+}
+
+/// One error per line, prefixed by the path and location like compilers do
+impl Display for FileErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, error) in self.errors.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            match (&self.path, error.location) {
+                (Some(path), Some(Location { line, column })) => write!(f, "{}:{}:{}: {}", path.display(), line, column, error.describe())?,
+                (Some(path), None) => write!(f, "{}: {}", path.display(), error.describe())?,
+                (None, _) => write!(f, "{}", error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FileErrors {}
+
+/// The candidate closest to a misspelled name, ignoring case, if it is close enough to be what was meant
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item=&'a str>) -> Option<String> {
+    let lowercase = name.to_lowercase();
+    let allowed = (name.chars().count() + 1) / 3;
+    candidates.into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(&lowercase, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= allowed)
+        .min()
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// Amount of characters to insert, remove, replace or swap with the next one to turn one text
+/// into the other
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    table[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = table[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            table[i][j] = replace.min(table[i - 1][j] + 1).min(table[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                table[i][j] = table[i][j].min(table[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    table[a.len()][b.len()]
+}
+
+/// A yaml line as `key: value`, with the columns of the key and the value
+fn key_value(line: &str) -> Option<(usize, &str, &str, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let (indent, rest) = match line[indent..].strip_prefix("- ") {
+        Some(rest) => (indent + 2 + rest.len() - rest.trim_start().len(), rest.trim_start()),
+        None => (indent, &line[indent..]),
+    };
+    let (key, value) = rest.split_once(':')?;
+    let value_column = indent + key.len() + 1 + value.len() - value.trim_start().len() + 1;
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    Some((indent + 1, key.trim(), value, value_column))
+}
+
+fn locate_in_yaml(lines: &[&str], subject: &Subject) -> Option<Location> {
+    let find = |matches: &dyn Fn(usize, &str, &str) -> bool| lines.iter()
+        .enumerate()
+        .find_map(|(index, line)| match key_value(line) {
+            Some((column, key, value, value_column)) if matches(column, key, value) => {
+                Some(Location { line: index + 1, column: if value.is_empty() { column } else { value_column } })
+            }
+            _ => None,
+        });
+    let find_card = |card: &str| {
+        let subroutine = card.split('/').next().unwrap_or(card);
+        find(&|column, key, value| column > 1 && key == "name" && value == card)
+            .or_else(|| find(&|column, key, value| column > 1 && key == "name" && value == subroutine))
+    };
+    match subject {
+        Subject::File => None,
+        Subject::Field(field) => find(&|column, key, _| column == 1 && key == *field),
+        Subject::Card(card) => find_card(card),
+        Subject::Reference { card, name } => {
+            let card = find_card(card);
+            let reference = card.and_then(|card| lines.iter().enumerate().skip(card.line - 1).find_map(|(index, line)| {
+                line.match_indices("next_card:").find_map(|(start, key)| {
+                    let value = &line[start + key.len()..];
+                    let value_start = start + key.len() + value.len() - value.trim_start().len();
+                    let unquoted = line[value_start..].trim_start_matches(['"', '\'']);
+                    let rest = unquoted.strip_prefix(name.as_str())?;
+                    rest.chars().next().is_none_or(|c| c.is_whitespace() || ",}\"'".contains(c))
+                        .then_some(Location { line: index + 1, column: line[..value_start].chars().count() + 1 })
+                })
+            }));
+            reference.or(card)
+        }
+    }
+}
+
+fn locate_in_text(lines: &[&str], subject: &Subject) -> Option<Location> {
+    let header = |keyword: &str| lines.iter()
+        .position(|line| line.split_whitespace().next() == Some(keyword) && !line.starts_with(char::is_whitespace))
+        .map(|index| Location { line: index + 1, column: 1 });
+    let find_card = |card: &str| {
+        let definition = |card: &str| lines.iter()
+            .position(|line| line.starts_with(&format!("{}:", card)) || line.starts_with(&format!("\"{}\":", card)))
+            .map(|index| Location { line: index + 1, column: 1 });
+        definition(card).or_else(|| definition(card.split('/').next().unwrap_or(card)))
+    };
+    match subject {
+        Subject::File => None,
+        Subject::Field(field) => header(match *field {
+            "name" => "program",
+            "initial_card" => "start",
+            other => other,
+        }),
+        Subject::Card(card) => find_card(card),
+        Subject::Reference { card, name } => {
+            let card = find_card(card);
+            let is_boundary = |c: Option<char>| c.is_none_or(|c| c.is_whitespace() || ",;|>\"".contains(c));
+            let reference = card.and_then(|card| lines.iter().enumerate().skip(card.line - 1).find_map(|(index, line)| {
+                line.match_indices(name.as_str())
+                    .filter(|&(start, _)| start > 0 && is_boundary(line[..start].chars().next_back()))
+                    .find(|&(start, _)| is_boundary(line[start + name.len()..].chars().next()))
+                    .map(|(start, _)| Location { line: index + 1, column: line[..start].chars().count() + 1 })
+            }));
+            reference.or(card)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::file_error::{suggest, FileErrors};
+    use crate::level_dto::LevelDto;
+    use crate::program::Program;
+    use crate::program_dto::ProgramDto;
+    use crate::program_text;
+
+    #[test]
+    fn suggests_close_names() {
+        assert_eq!(suggest("Frist", ["First", "Second"]), Some("First".to_string()));
+        assert_eq!(suggest("copy", ["Copy", "Cop"]), Some("Copy".to_string()));
+        assert_eq!(suggest("B", ["A", "C"]), None);
+        assert_eq!(suggest("Light", ["Dark"]), None);
+    }
+
+    #[test]
+    fn every_mistake_in_yaml_is_located() {
+        let yaml = "name: p
+initial_card: Strat
+cards:
+  - name: Start
+    instruction_on: { write_symbol: null, move_direction: Right, next_card: Ned }
+    instruction_off:
+      write_symbol: true
+      move_direction: Right
+      next_card: Bgein
+  - name: Begin
+    instruction_on: { write_symbol: null, move_direction: null, next_card: null }
+";
+        let dto: ProgramDto = serde_yaml::from_str(yaml).unwrap();
+        let errors = Program::try_from(dto).err().unwrap().locate(Path::new("p.yaml"), yaml);
+        assert_eq!(errors.to_string(), [
+            "p.yaml:2:15: Initial card not found: Strat. Did you mean Start?",
+            "p.yaml:5:77: Card does not exist: Ned",
+            "p.yaml:9:18: Card does not exist: Bgein. Did you mean Begin?",
+            "p.yaml:10:11: Card Begin has no instruction for when the tape is off",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn every_mistake_in_text_is_located() {
+        let text = "program p\nstart Srat\n\nStart: on -> R, Strat; off -> R, Ned\nStart: on -> halt; off -> halt\nEnd: on -> halt; off -> halt; oof -> halt\n";
+        let errors = program_text::parse(text).err().unwrap().locate(Path::new("p.tur"), text);
+        assert_eq!(errors.to_string(), [
+            "p.tur:2:7: Card does not exist: Srat",
+            "p.tur:4:17: Card does not exist: Strat. Did you mean Start?",
+            "p.tur:4:34: Card does not exist: Ned. Did you mean End?",
+            "p.tur:5:1: Duplicate card name: Start",
+            "p.tur:6:31: Expected on or off but found oof",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn yaml_errors_suggest_what_serde_expected() {
+        let yaml = "name: l\ndescription: d\nencoding:\n  type: Unari\ncases: []\n";
+        let error = FileErrors::from(serde_yaml::from_str::<LevelDto>(yaml).err().unwrap()).locate(Path::new("l.yaml"), yaml);
+        assert_eq!(error.to_string(), "l.yaml:4:9: encoding.type: unknown variant `Unari`, expected `Unary` or `Binary`. Did you mean Unary?");
+    }
+}
@@ -1,24 +1,81 @@
 use std::collections::HashSet;
 
-use crate::level::Target::{Position, TapeExact};
+use crate::level::Target::{GridExact, Position, Position2D, TapeExact};
 
 #[derive(Clone)]
 pub struct Level {
     pub name: String,
     pub description: String,
     pub cases: Vec<TestCase>,
+    pub solution: Option<String>,
+    pub hints: Vec<String>,
 }
 
 #[derive(Clone, Default)]
 pub struct TestCase {
     pub initial_tape: HashSet<i64>,
     pub target: Option<Target>,
+    /// For levels with more than one tape, the tapes other than the first one
+    pub other_tapes: Vec<TapeCase>,
+    /// Shape of the first tape
+    pub topology: Topology,
+    /// Lit cells of the first tape as `(x, y)`, when it is a grid
+    pub initial_grid: HashSet<(i64, i64)>,
+}
+
+/// The shape of a tape. Tapes other than the first one are always lines.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum Topology {
+    /// A line of cells, unbounded in both directions
+    #[default]
+    Line,
+    /// A line of cells from 0 to the right. A head moving left of 0 stays put, or falls off the
+    /// tape if `fall_off` is set.
+    SemiInfinite { fall_off: bool },
+    /// The cells from `from` to `to`, both included. A head moving past either end falls off.
+    Bounded { from: i64, to: i64 },
+    /// A ring of the cells from 0 to `length - 1`, where moving past one end arrives at the other.
+    Circular { length: i64 },
+    /// A two-dimensional grid of cells, unbounded in all directions. The head moves up and down
+    /// as well as left and right, and `y` grows downwards like the rows on a screen.
+    Grid,
+}
+
+/// Initial contents and target of one of the tapes of a test case.
+#[derive(Clone, Default)]
+pub struct TapeCase {
+    pub initial_tape: HashSet<i64>,
+    pub target: Option<Target>,
 }
 
 #[derive(Clone, Eq, PartialEq)]
 pub enum Target {
     TapeExact { tape: HashSet<i64> },
     Position { position: i64 },
+    GridExact { grid: HashSet<(i64, i64)> },
+    Position2D { x: i64, y: i64 },
+}
+
+impl Topology {
+    /// Where a head that moved to `position` on a line of this shape ends up, or `None` if it fell
+    /// off the tape. Grids are handled by the execution.
+    pub fn place(&self, position: i64) -> Option<i64> {
+        match *self {
+            Topology::Line | Topology::Grid => Some(position),
+            Topology::SemiInfinite { fall_off: false } => Some(position.max(0)),
+            Topology::SemiInfinite { fall_off: true } => (position >= 0).then_some(position),
+            Topology::Bounded { from, to } => (from..=to).contains(&position).then_some(position),
+            Topology::Circular { length } => position.checked_rem_euclid(length),
+        }
+    }
+
+    /// Whether `position` is a cell of a line of this shape
+    pub fn contains(&self, position: i64) -> bool {
+        match *self {
+            Topology::Circular { length } => (0..length).contains(&position),
+            _ => self.place(position) == Some(position),
+        }
+    }
 }
 
 impl Target {
@@ -29,4 +86,40 @@ impl Target {
     pub fn tape(tape: &[i64]) -> Target {
         TapeExact { tape: tape.iter().copied().collect() }
     }
+
+    pub fn grid(grid: &[(i64, i64)]) -> Target {
+        GridExact { grid: grid.iter().copied().collect() }
+    }
+
+    pub fn get_errors(&self, final_tape: &HashSet<i64>, final_position: i64) -> Vec<String> {
+        match self {
+            GridExact { .. } | Position2D { .. } => vec![format!("Only a grid can reach this target")],
+            TapeExact { tape } => if final_tape != tape {
+                vec![format!("Wrong final tape")]
+            } else {
+                vec![]
+            }
+            Position { position } => if &final_position != position {
+                vec![format!("Wrong final position: wanted {} but was {}", position, final_position)]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    pub fn get_grid_errors(&self, final_grid: &HashSet<(i64, i64)>, final_position: (i64, i64)) -> Vec<String> {
+        match self {
+            TapeExact { .. } | Position { .. } => vec![format!("Only a line can reach this target")],
+            GridExact { grid } => if final_grid != grid {
+                vec![format!("Wrong final grid")]
+            } else {
+                vec![]
+            }
+            Position2D { x, y } => if final_position != (*x, *y) {
+                vec![format!("Wrong final position: wanted ({}, {}) but was ({}, {})", x, y, final_position.0, final_position.1)]
+            } else {
+                vec![]
+            }
+        }
+    }
 }
\ No newline at end of file
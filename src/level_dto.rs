@@ -1,8 +1,12 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 
-use TargetDto::{Position, TapeExact};
+use TargetDto::{GridExact, Position, Position2D, TapeExact};
 
-use crate::level::{Level, Target, TestCase};
+use crate::encoding::Encoding;
+use crate::file_error::{FileError, FileErrors, Subject};
+use crate::level::{Level, TapeCase, Target, TestCase, Topology};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub struct LevelDto {
@@ -13,35 +17,119 @@ pub struct LevelDto {
     cases: Vec<TestCaseDto>,
     #[serde(default)]
     target: Option<TargetDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encoding: Option<EncodingDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    solution: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hints: Vec<String>,
+    /// Shape of the first tape of every test case, a line if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tape: Option<TopologyDto>,
 }
 
-impl From<LevelDto> for Level {
-    fn from(dto: LevelDto) -> Self {
+impl TryFrom<LevelDto> for Level {
+    type Error = FileErrors;
+
+    fn try_from(dto: LevelDto) -> Result<Self, Self::Error> {
+        let encoding = dto.encoding.map(Encoding::from).unwrap_or_default();
+        let topology = match dto.tape {
+            Some(tape) => Topology::try_from(tape)?,
+            None => Topology::default(),
+        };
         let cases = if dto.cases.is_empty() {
-            vec![TestCase::default()]
+            vec![TestCase {
+                target: dto.target.as_ref().map(Target::from),
+                topology,
+                ..TestCase::default()
+            }]
         } else {
             dto.cases
                 .iter()
-                .map(|tc: &TestCaseDto| TestCase {
-                    initial_tape: tc.initial_tape.iter().copied().collect(),
-                    target: tc.target.clone().or(dto.target.clone()).map(|target| Target::from(&target)),
+                .map(|tc: &TestCaseDto| {
+                    let (initial_tape, target) = tape(&tc.initial_tape, &tc.input, &tc.output, tc.target.as_ref().or(dto.target.as_ref()), encoding);
+                    let other_tapes = tc.other_tapes.iter()
+                        .map(|other| {
+                            let (initial_tape, target) = tape(&other.initial_tape, &other.input, &other.output, other.target.as_ref(), encoding);
+                            TapeCase { initial_tape, target }
+                        })
+                        .collect();
+                    TestCase {
+                        initial_tape,
+                        target,
+                        other_tapes,
+                        topology,
+                        initial_grid: tc.initial_grid.iter().map(|[x, y]| (*x, *y)).collect(),
+                    }
                 })
                 .collect()
         };
-        Level {
+        let errors: Vec<FileError> = cases.iter().enumerate().filter_map(|(index, case)| case_error(index, case)).collect();
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        Ok(Level {
             name: dto.name,
             description: dto.description,
             cases,
-        }
+            solution: dto.solution,
+            hints: dto.hints,
+        })
+    }
+}
+
+/// The first cell of the first tape of a test case that is lit or targeted but not on the tape
+fn case_error(index: usize, case: &TestCase) -> Option<FileError> {
+    if case.topology == Topology::Grid {
+        return None;
+    }
+    let off_tape = |cells: &mut dyn Iterator<Item=i64>| cells.filter(|&cell| !case.topology.contains(cell)).min();
+    if let Some(cell) = off_tape(&mut case.initial_tape.iter().copied()) {
+        return Some(FileError::new(Subject::Field("cases"), format!("Test case {} lights cell {}, which is not on the tape", index, cell)));
+    }
+    let target_cells: Vec<i64> = match &case.target {
+        Some(Target::TapeExact { tape }) => tape.iter().copied().collect(),
+        Some(Target::Position { position }) => vec![*position],
+        _ => Vec::new(),
+    };
+    off_tape(&mut target_cells.into_iter())
+        .map(|cell| FileError::new(Subject::Field("cases"), format!("The target of test case {} has cell {}, which is not on the tape", index, cell)))
+}
+
+/// Initial contents and target of a tape, where the input and output are encoded onto the tape.
+fn tape(initial_tape: &[i64], input: &Option<NumbersDto>, output: &Option<NumbersDto>, target: Option<&TargetDto>, encoding: Encoding) -> (HashSet<i64>, Option<Target>) {
+    let mut tape: HashSet<i64> = initial_tape.iter().copied().collect();
+    if let Some(input) = input {
+        tape.extend(encoding.encode(input.numbers()));
     }
+    let target = match output {
+        Some(output) => Some(Target::TapeExact { tape: encoding.encode(output.numbers()) }),
+        None => target.map(Target::from),
+    };
+    (tape, target)
 }
 
 impl From<Level> for LevelDto {
     fn from(level: Level) -> Self {
+        let tape = level.cases.first()
+            .map(|test_case| test_case.topology)
+            .filter(|topology| *topology != Topology::Line)
+            .map(TopologyDto::from);
         let to_test_case_dto = |test_case: TestCase| {
             TestCaseDto {
                 initial_tape: test_case.initial_tape.iter().copied().collect(),
                 target: test_case.target.map(TargetDto::from),
+                input: None,
+                output: None,
+                other_tapes: test_case.other_tapes.into_iter()
+                    .map(|other| TapeDto {
+                        initial_tape: other.initial_tape.iter().copied().collect(),
+                        target: other.target.map(TargetDto::from),
+                        input: None,
+                        output: None,
+                    })
+                    .collect(),
+                initial_grid: test_case.initial_grid.iter().map(|(x, y)| [*x, *y]).collect(),
             }
         };
 
@@ -50,6 +138,10 @@ impl From<Level> for LevelDto {
             description: level.description,
             cases: level.cases.into_iter().map(to_test_case_dto).collect(),
             target: None,
+            encoding: None,
+            solution: level.solution,
+            hints: level.hints,
+            tape,
         }
     }
 }
@@ -60,6 +152,114 @@ struct TestCaseDto {
     initial_tape: Vec<i64>,
     #[serde(default)]
     target: Option<TargetDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input: Option<NumbersDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<NumbersDto>,
+    /// For levels with more than one tape, the tapes other than the first one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    other_tapes: Vec<TapeDto>,
+    /// For levels on a grid, the lit cells of the first tape as `[x, y]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    initial_grid: Vec<[i64; 2]>,
+}
+
+/// One of the other tapes of a test case, described like the first tape.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+struct TapeDto {
+    #[serde(default)]
+    initial_tape: Vec<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<TargetDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input: Option<NumbersDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<NumbersDto>,
+}
+
+/// One or more numbers, written either as `5` or as `[2, 3]`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(untagged)]
+enum NumbersDto {
+    Single(u64),
+    Multiple(Vec<u64>),
+}
+
+impl NumbersDto {
+    fn numbers(&self) -> &[u64] {
+        match self {
+            NumbersDto::Single(number) => std::slice::from_ref(number),
+            NumbersDto::Multiple(numbers) => numbers,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(tag = "type")]
+enum EncodingDto {
+    Unary,
+    Binary {
+        #[serde(default)]
+        width: Option<u32>,
+    },
+}
+
+impl From<EncodingDto> for Encoding {
+    fn from(dto: EncodingDto) -> Self {
+        match dto {
+            EncodingDto::Unary => Encoding::Unary,
+            EncodingDto::Binary { width } => Encoding::Binary { width },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(tag = "type")]
+enum TopologyDto {
+    Line,
+    SemiInfinite {
+        #[serde(default)]
+        fall_off: bool,
+    },
+    Bounded {
+        from: i64,
+        to: i64,
+    },
+    Circular {
+        length: i64,
+    },
+    Grid,
+}
+
+impl TryFrom<TopologyDto> for Topology {
+    type Error = FileError;
+
+    fn try_from(dto: TopologyDto) -> Result<Self, Self::Error> {
+        let error = |message: String| Err(FileError::new(Subject::Field("tape"), message));
+        match dto {
+            TopologyDto::Line => Ok(Topology::Line),
+            TopologyDto::SemiInfinite { fall_off } => Ok(Topology::SemiInfinite { fall_off }),
+            TopologyDto::Bounded { from, to } if from > to => error(format!("A bounded tape cannot end at {} before it starts at {}", to, from)),
+            // The head always starts at 0
+            TopologyDto::Bounded { from, to } if !(from..=to).contains(&0) => error(format!("A bounded tape from {} to {} does not have cell 0 for the head to start on", from, to)),
+            TopologyDto::Bounded { from, to } => Ok(Topology::Bounded { from, to }),
+            TopologyDto::Circular { length } if length < 1 => error(format!("A circular tape must have a length of at least 1, not {}", length)),
+            TopologyDto::Circular { length } => Ok(Topology::Circular { length }),
+            TopologyDto::Grid => Ok(Topology::Grid),
+        }
+    }
+}
+
+impl From<Topology> for TopologyDto {
+    fn from(topology: Topology) -> Self {
+        match topology {
+            Topology::Line => TopologyDto::Line,
+            Topology::SemiInfinite { fall_off } => TopologyDto::SemiInfinite { fall_off },
+            Topology::Bounded { from, to } => TopologyDto::Bounded { from, to },
+            Topology::Circular { length } => TopologyDto::Circular { length },
+            Topology::Grid => TopologyDto::Grid,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
@@ -67,6 +267,8 @@ struct TestCaseDto {
 enum TargetDto {
     TapeExact { tape: Vec<i64> },
     Position { position: i64 },
+    GridExact { grid: Vec<[i64; 2]> },
+    Position2D { x: i64, y: i64 },
 }
 
 impl From<&TargetDto> for Target {
@@ -78,6 +280,10 @@ impl From<&TargetDto> for Target {
             Position { position } => Target::Position {
                 position: *position,
             },
+            GridExact { grid } => Target::GridExact {
+                grid: grid.iter().map(|[x, y]| (*x, *y)).collect(),
+            },
+            Position2D { x, y } => Target::Position2D { x: *x, y: *y },
         }
     }
 }
@@ -86,14 +292,17 @@ impl From<Target> for TargetDto {
     fn from(target: Target) -> Self {
         match target {
             Target::TapeExact { tape } => TapeExact { tape: tape.iter().copied().collect() },
-            Target::Position { position } => Position { position }
+            Target::Position { position } => Position { position },
+            Target::GridExact { grid } => GridExact { grid: grid.iter().map(|(x, y)| [*x, *y]).collect() },
+            Target::Position2D { x, y } => Position2D { x, y },
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::level_dto::{LevelDto, TestCaseDto};
+    use crate::level::{Level, Target, Topology};
+    use crate::level_dto::{EncodingDto, LevelDto, NumbersDto, TestCaseDto};
     use crate::level_dto::TargetDto::{Position, TapeExact};
 
     #[test]
@@ -103,6 +312,10 @@ mod tests {
             description: "".to_string(),
             cases: Vec::new(),
             target: None,
+            encoding: None,
+            solution: None,
+            hints: Vec::new(),
+            tape: None,
         };
         let string = include_str!("../res/level/sandbox.yaml");
         let actual: LevelDto = serde_yaml::from_str(string).unwrap();
@@ -118,17 +331,36 @@ mod tests {
                 TestCaseDto {
                     initial_tape: vec![3],
                     target: None,
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
                 },
                 TestCaseDto {
                     initial_tape: vec![8],
                     target: None,
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
                 },
                 TestCaseDto {
                     initial_tape: vec![0],
                     target: None,
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
                 },
             ],
             target: Some(TapeExact { tape: Vec::new() }),
+            encoding: None,
+            solution: Some("night_time_solution".to_string()),
+            hints: vec![
+                "The light is never to the left of where you start.".to_string(),
+                "A single card is enough: keep moving right while the tape is off.".to_string(),
+            ],
+            tape: None,
         };
         let string = include_str!("../res/level/night_time.yaml");
         let actual: LevelDto = serde_yaml::from_str(string).unwrap();
@@ -145,20 +377,140 @@ mod tests {
                 TestCaseDto {
                     initial_tape: vec![18],
                     target: Some(Position { position: 18 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
                 },
                 TestCaseDto {
                     initial_tape: vec![-13],
                     target: Some(Position { position: -13 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
                 },
                 TestCaseDto {
                     initial_tape: vec![0],
                     target: Some(Position { position: 0 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
+                },
+                TestCaseDto {
+                    initial_tape: vec![1],
+                    target: Some(Position { position: 1 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
+                },
+                TestCaseDto {
+                    initial_tape: vec![-1],
+                    target: Some(Position { position: -1 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
+                },
+                TestCaseDto {
+                    initial_tape: vec![2],
+                    target: Some(Position { position: 2 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
+                },
+                TestCaseDto {
+                    initial_tape: vec![-2],
+                    target: Some(Position { position: -2 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
+                },
+                TestCaseDto {
+                    initial_tape: vec![3],
+                    target: Some(Position { position: 3 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
+                },
+                TestCaseDto {
+                    initial_tape: vec![-3],
+                    target: Some(Position { position: -3 }),
+                    input: None,
+                    output: None,
+                    other_tapes: Vec::new(),
+                    initial_grid: Vec::new(),
                 },
             ],
             target: None,
+            encoding: None,
+            solution: Some("moth_solution".to_string()),
+            hints: vec![
+                "The light can be on either side, so you have to search both directions.".to_string(),
+                "Search back and forth, going a little further every time.".to_string(),
+                "Light a cell at each end of the area you have searched, so you know where to turn around.".to_string(),
+            ],
+            tape: None,
         };
         let string = include_str!("../res/level/moth.yaml");
         let actual: LevelDto = serde_yaml::from_str(string).unwrap();
         assert_eq!(expected, actual)
     }
+
+    #[test]
+    fn deserialize_addition() {
+        let string = include_str!("../res/level/addition.yaml");
+        let dto: LevelDto = serde_yaml::from_str(string).unwrap();
+        assert_eq!(dto.encoding, Some(EncodingDto::Unary));
+        assert_eq!(dto.cases[0].input, Some(NumbersDto::Multiple(vec![2, 3])));
+        assert_eq!(dto.cases[0].output, Some(NumbersDto::Single(5)));
+
+        let level = Level::try_from(dto).unwrap();
+        assert_eq!(level.cases[0].initial_tape, vec![0, 1, 3, 4, 5].into_iter().collect());
+        assert!(level.cases[0].target == Some(Target::tape(&[0, 1, 2, 3, 4])));
+    }
+
+    #[test]
+    fn deserialize_binary_increment() {
+        let string = include_str!("../res/level/binary_increment.yaml");
+        let level = Level::try_from(serde_yaml::from_str::<LevelDto>(string).unwrap()).unwrap();
+        assert_eq!(level.cases[3].initial_tape, vec![0, 1, 2].into_iter().collect());
+        assert!(level.cases[3].target == Some(Target::tape(&[3])));
+    }
+
+    #[test]
+    fn deserialize_tape_shapes() {
+        let ring = Level::try_from(serde_yaml::from_str::<LevelDto>(include_str!("../res/level/ring.yaml")).unwrap()).unwrap();
+        assert_eq!(ring.cases[0].topology, Topology::Circular { length: 8 });
+        let box_level = Level::try_from(serde_yaml::from_str::<LevelDto>(include_str!("../res/level/box.yaml")).unwrap()).unwrap();
+        assert_eq!(box_level.cases[0].topology, Topology::Grid);
+        assert!(box_level.cases[0].target == Some(Target::grid(&[(0, 0), (1, 0), (1, 1), (0, 1)])));
+
+        let yaml = "name: half\ntape:\n  type: SemiInfinite\ncases:\n  - initial_tape: [2]";
+        let half = Level::try_from(serde_yaml::from_str::<LevelDto>(yaml).unwrap()).unwrap();
+        assert_eq!(half.cases[0].topology, Topology::SemiInfinite { fall_off: false });
+    }
+
+    #[test]
+    fn tapes_that_cannot_be_used_are_rejected() {
+        let error = |tape: &str, cases: &str| {
+            let yaml = format!("name: broken\ntape: {}\ncases: {}\n", tape, cases);
+            let errors = Level::try_from(serde_yaml::from_str::<LevelDto>(&yaml).unwrap()).err().unwrap();
+            errors.errors.iter().map(|error| error.message.clone()).collect::<Vec<_>>().join("\n")
+        };
+        assert_eq!(error("{ type: Circular, length: 0 }", "[]"), "A circular tape must have a length of at least 1, not 0");
+        assert_eq!(error("{ type: Circular, length: -8 }", "[]"), "A circular tape must have a length of at least 1, not -8");
+        assert_eq!(error("{ type: Bounded, from: 5, to: 10 }", "[]"), "A bounded tape from 5 to 10 does not have cell 0 for the head to start on");
+        assert_eq!(error("{ type: Bounded, from: 3, to: -3 }", "[]"), "A bounded tape cannot end at -3 before it starts at 3");
+        assert_eq!(error("{ type: Circular, length: 8 }", "[{ initial_tape: [1, 9] }]"), "Test case 0 lights cell 9, which is not on the tape");
+        assert_eq!(
+            error("{ type: Bounded, from: 0, to: 4 }", "[{ initial_tape: [1] }, { target: { type: Position, position: -1 } }]"),
+            "The target of test case 1 has cell -1, which is not on the tape",
+        );
+    }
 }
@@ -7,7 +7,8 @@ use crate::level_dto::LevelDto;
 fn sandbox() -> Level {
     serde_yaml::from_str::<LevelDto>(include_str!("../res/level/sandbox.yaml"))
         .unwrap()
-        .into()
+        .try_into()
+        .unwrap()
 }
 
 fn move_eight_right() -> Level {
@@ -18,29 +19,87 @@ fn move_eight_right() -> Level {
             TestCase {
                 initial_tape: vec![8].into_iter().collect(),
                 target: Some(Target::position(8)),
+                ..TestCase::default()
             },
             TestCase {
                 initial_tape: Default::default(),
                 target: Some(Target::position(8)),
+                ..TestCase::default()
             },
             TestCase {
                 initial_tape: vec![2, 3, 5, 7, 11, 13].into_iter().collect(),
                 target: Some(Target::position(8)),
+                ..TestCase::default()
             },
         ],
+        solution: Some("move8_solution".to_string()),
+        hints: vec![
+            "Each card can only move one step.".to_string(),
+        ],
     }
 }
 
 fn night_time() -> Level {
     serde_yaml::from_str::<LevelDto>(include_str!("../res/level/night_time.yaml"))
         .unwrap()
-        .into()
+        .try_into()
+        .unwrap()
 }
 
 fn moth() -> Level {
     serde_yaml::from_str::<LevelDto>(include_str!("../res/level/moth.yaml"))
         .unwrap()
-        .into()
+        .try_into()
+        .unwrap()
+}
+
+fn increment() -> Level {
+    serde_yaml::from_str::<LevelDto>(include_str!("../res/level/increment.yaml"))
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn addition() -> Level {
+    serde_yaml::from_str::<LevelDto>(include_str!("../res/level/addition.yaml"))
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn binary_increment() -> Level {
+    serde_yaml::from_str::<LevelDto>(include_str!("../res/level/binary_increment.yaml"))
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn copy() -> Level {
+    serde_yaml::from_str::<LevelDto>(include_str!("../res/level/copy.yaml"))
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn box_level() -> Level {
+    serde_yaml::from_str::<LevelDto>(include_str!("../res/level/box.yaml"))
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn lucky_moth() -> Level {
+    serde_yaml::from_str::<LevelDto>(include_str!("../res/level/lucky_moth.yaml"))
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn ring() -> Level {
+    serde_yaml::from_str::<LevelDto>(include_str!("../res/level/ring.yaml"))
+        .unwrap()
+        .try_into()
+        .unwrap()
 }
 
 pub fn builtins() -> Vec<Level> {
@@ -49,5 +108,51 @@ pub fn builtins() -> Vec<Level> {
         move_eight_right(),
         night_time(),
         moth(),
+        increment(),
+        addition(),
+        binary_increment(),
+        copy(),
+        box_level(),
+        lucky_moth(),
+        ring(),
     ]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution::LevelExecution;
+    use crate::levels::builtins;
+    use crate::{nondeterminism, programs};
+
+    #[test]
+    fn solutions_solve_their_levels() {
+        for level in builtins() {
+            let Some(solution) = level.solution.clone() else {
+                continue;
+            };
+            let program = programs::builtins()
+                .into_iter()
+                .find(|program| program.name == solution)
+                .unwrap_or_else(|| panic!("Solution {} of level {} not found", solution, level.name));
+            let level_name = level.name.clone();
+            let execution = if program.is_nondeterministic() {
+                nondeterminism::explore(level, program, 10_000, nondeterminism::DEFAULT_MAX_BRANCHES).0
+            } else {
+                let mut execution = LevelExecution::new(level, program);
+                execution.run(10_000);
+                execution
+            };
+            assert!(execution.is_terminated(), "Solution {} of level {} did not halt", solution, level_name);
+            assert!(execution.is_solved(), "Solution {} does not solve level {}", solution, level_name);
+        }
+    }
+
+    #[test]
+    fn levels_with_targets_have_solutions() {
+        for level in builtins() {
+            if level.cases.iter().any(|tc| tc.target.is_some()) {
+                assert!(level.solution.is_some(), "Level {} has no solution", level.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! The engine behind the `tur` command line game: programs of cards that move a head over an
+//! infinite tape of lights, levels with test cases for them, and tools to run and analyse both.
+//!
+//! The types re-exported here are the supported interface. The hidden modules only serve the
+//! command line game and may change with it.
+
+pub use data_dir::DataDir;
+pub use execution::{ExecutionObserver, LevelExecution, TestCaseExecution, TestCaseExecutionState};
+pub use file_error::{FileError, FileErrors};
+pub use level::{Level, TapeCase, Target, TestCase, Topology};
+pub use level_dto::LevelDto;
+pub use loader::{read_level, read_pack, read_program};
+pub use pack::Pack;
+pub use program::{Card, Direction, Instruction, Program, TapeAction};
+pub use program_dto::ProgramDto;
+pub use progress::Progress;
+
+pub mod beaver;
+pub mod breakpoint;
+mod compiler;
+pub mod coverage;
+pub mod data_dir;
+pub mod decider;
+mod encoding;
+pub mod equivalence;
+pub mod execution;
+pub mod file_error;
+pub mod level;
+pub mod level_dto;
+pub mod levels;
+pub mod lint;
+pub mod loader;
+pub mod macro_execution;
+pub mod minimise;
+pub mod nondeterminism;
+pub mod pack;
+mod pack_dto;
+pub mod packs;
+#[doc(hidden)]
+pub mod parallel;
+pub mod profile;
+pub mod program;
+pub mod program_dto;
+pub mod program_text;
+pub mod programs;
+pub mod progress;
+#[doc(hidden)]
+pub mod random;
+#[doc(hidden)]
+pub mod render;
+pub mod synthesis;
+#[doc(hidden)]
+pub mod tui;
@@ -0,0 +1,213 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde::Serialize;
+
+use crate::program::{Direction, Instruction, Program};
+
+#[derive(Serialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub card: Option<String>,
+    pub message: String,
+}
+
+/// Inspects a program without running it, most severe diagnostics first.
+pub fn lint(program: &Program) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let reachable = reachable_from(program, program.initial_card);
+    let halting = halting_cards(program);
+
+    for (index, card) in program.cards.iter().enumerate() {
+        let diagnostic = |severity, code, message: String| Diagnostic {
+            severity,
+            code,
+            card: Some(card.name.clone()),
+            message,
+        };
+
+        let referenced = index == program.initial_card || program.cards.iter()
+            .enumerate()
+            .filter(|&(other, _)| other != index)
+            .any(|(_, other)| other.next_cards().any(|next| next == index));
+        if !referenced {
+            diagnostics.push(diagnostic(Severity::Info, "unused-card", format!("Card {} is never used by another card", card.name)));
+        } else if !reachable.contains(&index) {
+            diagnostics.push(diagnostic(Severity::Warning, "unreachable-card", format!("Card {} cannot be reached from the initial card", card.name)));
+        } else if !halting.contains(&index) {
+            diagnostics.push(diagnostic(Severity::Warning, "never-halts", format!("Once card {} is reached, the program can never halt", card.name)));
+        }
+
+        for (read, instruction) in card.instructions().enumerate() {
+            if is_no_op_loop(index, read, instruction) {
+                let symbol = |tape: usize| if read >> tape & 1 == 1 { "on" } else { "off" };
+                let condition = if program.tapes() == 1 {
+                    format!("the tape is {}", symbol(0))
+                } else {
+                    format!("the heads read {}", (0..program.tapes()).map(symbol).collect::<Vec<_>>().join(", "))
+                };
+                diagnostics.push(diagnostic(Severity::Error, "infinite-loop", format!("Card {} loops forever without changing anything when {}", card.name, condition)));
+            }
+        }
+    }
+
+    if halting.is_disjoint(&reachable) {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            code: "missing-halt",
+            card: None,
+            message: "No reachable instruction halts the program".to_string(),
+        });
+    }
+
+    diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity));
+    diagnostics
+}
+
+fn successors(program: &Program, index: usize) -> impl Iterator<Item=usize> + '_ {
+    program.cards[index].next_cards()
+}
+
+fn reachable_from(program: &Program, start: usize) -> HashSet<usize> {
+    let mut reachable = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(index) = queue.pop_front() {
+        for next in successors(program, index) {
+            if reachable.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    reachable
+}
+
+/// Cards from which some sequence of instructions leads to a halt.
+fn halting_cards(program: &Program) -> HashSet<usize> {
+    let mut halting: HashSet<usize> = program.cards.iter()
+        .enumerate()
+        .filter(|(_, card)| card.instructions().any(|instruction| instruction.next_card.is_none()))
+        .map(|(index, _)| index)
+        .collect();
+    loop {
+        let before = halting.len();
+        for index in 0..program.cards.len() {
+            if successors(program, index).any(|next| halting.contains(&next)) {
+                halting.insert(index);
+            }
+        }
+        if halting.len() == before {
+            return halting;
+        }
+    }
+}
+
+/// Whether the instruction, for heads that read `read` as in `Card::instruction`, continues
+/// with the same card without writing anything new or moving any head.
+fn is_no_op_loop(index: usize, read: usize, instruction: &Instruction) -> bool {
+    let unchanged = |tape: usize, write_symbol: Option<bool>, move_direction: Option<Direction>| {
+        move_direction.is_none() && write_symbol.is_none_or(|symbol| symbol == (read >> tape & 1 == 1))
+    };
+    instruction.next_card == Some(index)
+        && unchanged(0, instruction.write_symbol, instruction.move_direction)
+        && instruction.other_tapes.iter().enumerate().all(|(tape, action)| unchanged(tape + 1, action.write_symbol, action.move_direction))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::{lint, Severity};
+    use crate::program::{Card, Direction, Instruction, Program};
+    use crate::programs;
+
+    fn instruction(write_symbol: Option<bool>, move_direction: Option<Direction>, next_card: Option<usize>) -> Instruction {
+        Instruction {
+            write_symbol,
+            move_direction,
+            next_card,
+            other_tapes: Vec::new(),
+        }
+    }
+
+    fn program(cards: Vec<Card>) -> Program {
+        Program {
+            name: "test".to_string(),
+            description: String::new(),
+            initial_card: 0,
+            cards,
+        }
+    }
+
+    fn codes(program: &Program) -> Vec<(&'static str, Option<String>)> {
+        lint(program).into_iter().map(|diagnostic| (diagnostic.code, diagnostic.card)).collect()
+    }
+
+    #[test]
+    fn solutions_are_clean() {
+        for program in programs::builtins().iter().filter(|program| program.name.ends_with("_solution")) {
+            assert!(codes(program).is_empty(), "{}: {:?}", program.name, codes(program));
+        }
+    }
+
+    #[test]
+    fn light_the_world_never_halts() {
+        let program = programs::builtins().into_iter().find(|program| program.name == "light_the_world").unwrap();
+        let codes = codes(&program);
+        assert!(codes.contains(&("never-halts", Some("LEFT".to_string()))));
+        assert!(codes.contains(&("never-halts", Some("RIGHT".to_string()))));
+        assert!(codes.contains(&("missing-halt", None)));
+    }
+
+    #[test]
+    fn unused_and_unreachable() {
+        let program = program(vec![
+            Card {
+                name: "A".to_string(),
+                tape_on: instruction(None, None, None),
+                tape_off: instruction(None, None, None),
+                other_instructions: Vec::new(),
+                alternatives: Vec::new(),
+            },
+            Card {
+                name: "B".to_string(),
+                tape_on: instruction(None, Some(Direction::Left), Some(2)),
+                tape_off: instruction(None, Some(Direction::Left), Some(1)),
+                other_instructions: Vec::new(),
+                alternatives: Vec::new(),
+            },
+            Card {
+                name: "C".to_string(),
+                tape_on: instruction(None, None, None),
+                tape_off: instruction(None, None, None),
+                other_instructions: Vec::new(),
+                alternatives: Vec::new(),
+            },
+        ]);
+        assert_eq!(codes(&program), vec![
+            ("unreachable-card", Some("C".to_string())),
+            ("unused-card", Some("B".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn no_op_loop() {
+        let program = program(vec![
+            Card {
+                name: "A".to_string(),
+                tape_on: instruction(Some(true), None, Some(0)),
+                tape_off: instruction(Some(true), None, Some(0)),
+                other_instructions: Vec::new(),
+                alternatives: Vec::new(),
+            },
+        ]);
+        let diagnostics = lint(&program);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "Card A loops forever without changing anything when the tape is on");
+        assert_eq!(diagnostics.iter().filter(|diagnostic| diagnostic.code == "infinite-loop").count(), 1);
+    }
+}
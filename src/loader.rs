@@ -0,0 +1,97 @@
+use std::fs;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::compiler;
+use crate::file_error::{FileError, FileErrors, Subject};
+use crate::level::Level;
+use crate::level_dto::LevelDto;
+use crate::levels;
+use crate::pack::Pack;
+use crate::pack_dto::PackDto;
+use crate::program::Program;
+use crate::program_dto::ProgramDto;
+use crate::program_text;
+use crate::programs;
+
+/// Extensions of the files programs are read from
+pub const PROGRAM_EXTENSIONS: [&str; 3] = ["yaml", "tur", "tl"];
+
+/// Reads a program written as yaml, in the text format with a `.tur` extension, or in the
+/// structured language with a `.tl` extension. The programs its subroutine cards run are looked
+/// for next to it in the same directory, or else among the builtin programs.
+///
+/// Fails with every mistake found in the file, located in its text.
+pub fn read_program(path: &Path) -> Result<Program, FileErrors> {
+    let text = read_text(path)?;
+    let dto = parse_program(path, &text).map_err(|errors| errors.locate(path, &text))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let find = |name: &str| -> Result<Option<ProgramDto>, FileErrors> {
+        for extension in PROGRAM_EXTENSIONS {
+            let sibling = dir.join(format!("{}.{}", name, extension));
+            if sibling.is_file() {
+                let text = read_text(&sibling)?;
+                return parse_program(&sibling, &text)
+                    .map(Some)
+                    .map_err(|errors| errors.locate(&sibling, &text));
+            }
+        }
+        Ok(programs::builtins().into_iter().find(|program| program.name == name).map(ProgramDto::from))
+    };
+    dto.resolve(&find).map_err(|errors| errors.locate(path, &text))
+}
+
+fn parse_program(path: &Path, text: &str) -> Result<ProgramDto, FileErrors> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tur") => program_text::parse(text),
+        Some("tl") => compiler::compile(text).map(ProgramDto::from),
+        _ => Ok(serde_yaml::from_str(text)?),
+    }
+}
+
+fn read_text(path: &Path) -> Result<String, FileErrors> {
+    fs::read_to_string(path).map_err(|err| FileErrors {
+        path: Some(path.to_path_buf()),
+        errors: vec![FileError::new(Subject::File, format!("Unable to read the file: {}", err))],
+    })
+}
+
+/// Reads a level, failing with the location of the mistake in the file
+pub fn read_level(path: &Path) -> Result<Level, FileErrors> {
+    let text = read_text(path)?;
+    let dto: LevelDto = serde_yaml::from_str(&text).map_err(|err| FileErrors::from(err).locate(path, &text))?;
+    Level::try_from(dto).map_err(|errors| errors.locate(path, &text))
+}
+
+/// Reads every yaml file in a directory as a level, except for pack manifests.
+pub fn read_levels(dir: &Path) -> Result<Vec<Level>> {
+    let mut levels = Vec::new();
+    for path in level_files(dir)? {
+        levels.push(read_level(&path)?);
+    }
+    Ok(levels)
+}
+
+/// The yaml files in a directory that are levels, which is all of them except for pack manifests
+pub fn level_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|name| name != "pack.yaml") && path.extension().is_some_and(|extension| extension == "yaml") {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Reads a pack directory: a pack.yaml manifest next to one yaml file per level.
+/// Manifests may also refer to builtin levels.
+pub fn read_pack(dir: &Path) -> Result<Pack> {
+    let manifest = fs::read_to_string(dir.join("pack.yaml"))?;
+    let dto: PackDto = serde_yaml::from_str(&manifest)?;
+    let mut levels = levels::builtins();
+    levels.extend(read_levels(dir)?);
+    Ok(dto.try_into_pack(&levels)?)
+}
@@ -0,0 +1,599 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::level::TestCase;
+use crate::program::{Direction, Program};
+
+/// Most steps of the program that one repetition of a macro step may take
+const PERIOD_LIMIT: usize = 256;
+/// Most cells in a block that the cards may repeat a period over
+const BLOCK_LIMIT: usize = 8;
+
+/// `repeat` copies of a block of cells, listed from the one closest to the head outwards.
+/// Blocks of equal cells are always a single cell, so a run of equal cells is one `Run`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Run {
+    cells: Vec<bool>,
+    repeat: u64,
+}
+
+impl Run {
+    fn length(&self) -> u64 {
+        (self.cells.len() as u64).saturating_mul(self.repeat)
+    }
+}
+
+/// What a card does when it starts on a tape that repeats a block in front of the head: after
+/// `steps` steps it is the same card again, `cells.len()` cells further, in front of the same
+/// repeating cells, and it has turned the cells it passed into `cells`, nearest to the start first.
+/// It never looked behind where it started, nor at the cells it has not passed yet, so it does the
+/// same again for as long as the block keeps repeating.
+#[derive(Clone, Debug)]
+struct Period {
+    cells: Vec<bool>,
+    steps: u64,
+}
+
+/// An alternative to `TestCaseExecution` for programs that run for a very long time.
+///
+/// The tape is stored as runs of repeated blocks of cells on either side of the head. When the
+/// cards keep going the same way over a repeating block and come back to the same card in front
+/// of the same cells, like a single card sweeping over equal cells, or the cards of a busy beaver
+/// going back and forth over the pattern they left behind, they would do that for every
+/// repetition of the block. All those repetitions are taken in a single step of the execution,
+/// while counting all of the individual steps.
+///
+/// The final tape, position and amount of steps are the same as for `TestCaseExecution`.
+/// Only programs with one tape can be run this way, and only on test cases where that tape is a line.
+pub struct MacroExecution {
+    /// Runs to the left of the head, closest run last
+    left: Vec<Run>,
+    /// Runs to the right of the head, closest run last
+    right: Vec<Run>,
+    head: bool,
+    position: i64,
+    current_card_index: Option<usize>,
+    /// Card and symbol of the instruction that halted the program
+    halted_on: Option<(usize, bool)>,
+    /// Whether the head moved past the last cell there is
+    fell_off: bool,
+    pub steps: u64,
+    program: Program,
+    /// Periods by card, whether it goes right, and repeating block as a `block_key`, found so far
+    periods: HashMap<(usize, bool, u32), Option<Period>>,
+}
+
+impl MacroExecution {
+    pub fn new(tc: TestCase, program: Program) -> MacroExecution {
+        let mut lit: Vec<i64> = tc.initial_tape.into_iter().collect();
+        lit.sort();
+        let head = lit.binary_search(&0).is_ok();
+        let split = lit.partition_point(|&position| position < 0);
+        // Stacks are built from the far end inwards, with the unlit cells between lit ones
+        let mut left = Vec::new();
+        let mut next = lit.first().copied().unwrap_or_default();
+        for &position in &lit[..split] {
+            push(&mut left, &[false], position.abs_diff(next));
+            push(&mut left, &[true], 1);
+            next = position + 1;
+        }
+        push(&mut left, &[false], next.unsigned_abs());
+        let mut right = Vec::new();
+        let mut next = lit.last().copied().unwrap_or_default();
+        for &position in lit[split..].iter().rev().filter(|&&position| position > 0) {
+            push(&mut right, &[false], next.abs_diff(position));
+            push(&mut right, &[true], 1);
+            next = position - 1;
+        }
+        push(&mut right, &[false], next.max(0).unsigned_abs());
+        MacroExecution {
+            left,
+            right,
+            head,
+            position: 0,
+            current_card_index: Some(program.initial_card),
+            halted_on: None,
+            fell_off: false,
+            steps: 0,
+            program,
+            periods: HashMap::new(),
+        }
+    }
+
+    /// Executes the current instruction, or as many repetitions of a period as there are, but
+    /// never more than `max_steps` steps.
+    pub fn step(&mut self, max_steps: u64) {
+        let Some(index) = self.current_card_index else {
+            return;
+        };
+        if max_steps == 0 || self.repeat_period(index, max_steps) {
+            return;
+        }
+        let card = &self.program.cards[index];
+        let on = self.head;
+        let instruction = card.instruction(on as usize).clone();
+        let written = instruction.write_symbol.unwrap_or(on);
+        self.head = written;
+        // Up and down leave the head where it is on a line
+        if let Some(direction @ (Direction::Left | Direction::Right)) = instruction.move_direction {
+            let position = match direction {
+                Direction::Left => self.position.checked_sub(1),
+                _ => self.position.checked_add(1),
+            };
+            let Some(position) = position else {
+                self.fell_off = true;
+                self.current_card_index = None;
+                self.steps += 1;
+                return;
+            };
+            let (behind, ahead) = self.sides(direction);
+            push(behind, &[written], 1);
+            self.head = take(ahead, 1);
+            self.position = position;
+        }
+        self.advance(index, on, instruction.next_card, 1);
+    }
+
+    /// Takes every repetition of the period of the card that fits in front of the head, if there
+    /// is one, and tells whether it did.
+    fn repeat_period(&mut self, index: usize, max_steps: u64) -> bool {
+        for direction in [Direction::Right, Direction::Left] {
+            let ahead = if direction == Direction::Left { &self.left } else { &self.right };
+            let nearest = nearest_cells(self.head, ahead, BLOCK_LIMIT);
+            for width in 1..=BLOCK_LIMIT {
+                let block = &nearest[..width];
+                // Blocks that repeat a shorter block have the periods of the shorter one
+                if root_width(block) < width {
+                    continue;
+                }
+                let program = &self.program;
+                let period = self.periods
+                    .entry((index, direction == Direction::Right, block_key(block)))
+                    .or_insert_with(|| find_period(program, index, direction, block));
+                let Some(Period { cells, steps }) = period else {
+                    continue;
+                };
+                let (cells_width, steps) = (cells.len() as u64, *steps);
+                let to_end = match direction {
+                    Direction::Left => self.position.abs_diff(i64::MIN),
+                    _ => self.position.abs_diff(i64::MAX),
+                };
+                let available = repeated_length(block, ahead);
+                let repetitions = (available / cells_width).min(max_steps / steps).min(to_end / cells_width);
+                if repetitions == 0 {
+                    continue;
+                }
+                let distance = repetitions * cells_width;
+                let position = match direction {
+                    Direction::Left => self.position.checked_sub_unsigned(distance),
+                    _ => self.position.checked_add_unsigned(distance),
+                };
+                let Some(position) = position else {
+                    continue;
+                };
+                let mut passed = cells.clone();
+                passed.reverse();
+                let (behind, ahead) = self.sides(direction);
+                push(behind, &passed, repetitions);
+                take(ahead, distance - 1);
+                self.head = take(ahead, 1);
+                self.position = position;
+                self.steps += repetitions * steps;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The runs behind the head and in front of it when it moves in the direction
+    fn sides(&mut self, direction: Direction) -> (&mut Vec<Run>, &mut Vec<Run>) {
+        match direction {
+            Direction::Left => (&mut self.right, &mut self.left),
+            _ => (&mut self.left, &mut self.right),
+        }
+    }
+
+    fn advance(&mut self, index: usize, on: bool, next_card: Option<usize>, steps: u64) {
+        self.current_card_index = next_card;
+        self.steps += steps;
+        if next_card.is_none() {
+            self.halted_on = Some((index, on));
+        }
+    }
+
+    pub fn run(&mut self, max_steps: u64) -> bool {
+        let limit = self.steps.saturating_add(max_steps);
+        while !self.is_terminated() && self.steps < limit {
+            self.step(limit - self.steps);
+        }
+        self.is_terminated()
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.current_card_index.is_none()
+    }
+
+    /// Whether the head moved past the last cell there is, which ends the execution without halting
+    pub fn fell_off(&self) -> bool {
+        self.fell_off
+    }
+
+    pub fn get_current_position(&self) -> i64 {
+        self.position
+    }
+
+    pub fn get_halted_on(&self) -> Option<(usize, bool)> {
+        self.halted_on
+    }
+
+    /// Amount of lit cells on the tape
+    pub fn count_lit(&self) -> u64 {
+        let lit = |runs: &Vec<Run>| runs.iter()
+            .map(|run| (run.cells.iter().filter(|&&on| on).count() as u64).saturating_mul(run.repeat))
+            .fold(0u64, u64::saturating_add);
+        lit(&self.left).saturating_add(lit(&self.right)).saturating_add(self.head as u64)
+    }
+
+    pub fn get_tape(&self) -> HashSet<i64> {
+        let mut tape = HashSet::new();
+        if self.head {
+            tape.insert(self.position);
+        }
+        for (runs, sign) in [(&self.left, -1), (&self.right, 1)] {
+            let mut distance = 1;
+            for run in runs.iter().rev() {
+                for _ in 0..run.repeat {
+                    for &on in &run.cells {
+                        if on {
+                            tape.insert(self.position + sign * distance);
+                        }
+                        distance += 1;
+                    }
+                }
+            }
+        }
+        tape
+    }
+}
+
+/// The head and the `count - 1` cells in front of it, closest first
+fn nearest_cells(head: bool, ahead: &[Run], count: usize) -> Vec<bool> {
+    let mut cells = vec![head];
+    'runs: for run in ahead.iter().rev() {
+        for _ in 0..run.repeat {
+            for &on in &run.cells {
+                if cells.len() == count {
+                    break 'runs;
+                }
+                cells.push(on);
+            }
+        }
+    }
+    // Past the runs every cell is unlit
+    cells.resize(count, false);
+    cells
+}
+
+/// Amount of cells from the head on that repeat the block, which starts at the head
+fn repeated_length(block: &[bool], ahead: &[Run]) -> u64 {
+    let width = block.len();
+    let mut length = 1u64;
+    let mut phase = 1 % width;
+    for run in ahead.iter().rev() {
+        // Both the run and the block repeat every `lcm` cells, so if those match, all of them do
+        let cells = run.cells.len();
+        let lcm = cells / gcd(cells, width) * width;
+        let checked = run.length().min(lcm as u64) as usize;
+        if let Some(offset) = (0..checked).find(|&offset| run.cells[offset % cells] != block[(phase + offset) % width]) {
+            return length + offset as u64;
+        }
+        length = length.saturating_add(run.length());
+        phase = (phase + cells % width * (run.repeat % width as u64) as usize) % width;
+    }
+    // Past the runs every cell is unlit, which only a block of unlit cells repeats forever
+    match (0..width).find(|&offset| block[(phase + offset) % width]) {
+        Some(offset) => length.saturating_add(offset as u64),
+        None => u64::MAX,
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// The shortest width of a block that the block repeats
+fn root_width(block: &[bool]) -> usize {
+    (1..=block.len())
+        .find(|&width| block.len().is_multiple_of(width) && block.iter().enumerate().all(|(index, &on)| on == block[index % width]))
+        .unwrap_or(block.len())
+}
+
+/// A block of at most `BLOCK_LIMIT` cells as a number, with a leading bit set so that blocks of
+/// different lengths differ
+fn block_key(block: &[bool]) -> u32 {
+    block.iter().fold(1, |key, &on| key << 1 | on as u32)
+}
+
+/// Runs the program from the card on a tape that repeats the block from the head on in the
+/// direction, until it is back at the card on a cell it has not been on before, in front of the
+/// same repetition of the block.
+fn find_period(program: &Program, card: usize, direction: Direction, block: &[bool]) -> Option<Period> {
+    let mut cells: Vec<bool> = (0..=PERIOD_LIMIT).map(|offset| block[offset % block.len()]).collect();
+    let mut offset = 0;
+    let mut furthest = 0;
+    let mut current = card;
+    for steps in 1..=PERIOD_LIMIT {
+        let instruction = program.cards[current].instruction(cells[offset] as usize);
+        if let Some(symbol) = instruction.write_symbol {
+            cells[offset] = symbol;
+        }
+        offset = match instruction.move_direction {
+            Some(moved) if moved == direction => offset + 1,
+            // Periods never look behind where they start
+            Some(Direction::Left | Direction::Right) => offset.checked_sub(1)?,
+            _ => offset,
+        };
+        current = instruction.next_card?;
+        if offset > furthest {
+            if current == card && offset % block.len() == 0 {
+                cells.truncate(offset);
+                return Some(Period { cells, steps: steps as u64 });
+            }
+            furthest = offset;
+        }
+    }
+    None
+}
+
+/// Puts `repeat` copies of the block next to the head, with the first cell of the block closest to
+/// the head, merging them with the closest run if possible.
+fn push(runs: &mut Vec<Run>, block: &[bool], repeat: u64) {
+    if repeat == 0 || block.is_empty() {
+        return;
+    }
+    // The shortest block that the block itself repeats
+    let width = root_width(block);
+    let repeat = repeat.saturating_mul((block.len() / width) as u64);
+    let block = &block[..width];
+    match runs.last_mut() {
+        Some(run) if run.cells == block => run.repeat = run.repeat.saturating_add(repeat),
+        _ => runs.push(Run { cells: block.to_vec(), repeat }),
+    }
+    // Unlit cells at the far end are the same as no cells at all
+    if runs.len() == 1 && runs[0].cells == [false] {
+        runs.clear();
+    }
+}
+
+/// Removes `length` cells next to the head, and returns the last one removed.
+fn take(runs: &mut Vec<Run>, length: u64) -> bool {
+    let mut remaining = length;
+    let mut on = false;
+    while remaining > 0 {
+        let Some(run) = runs.last_mut() else {
+            return false;
+        };
+        if remaining >= run.length() {
+            remaining -= run.length();
+            on = run.cells[run.cells.len() - 1];
+            runs.pop();
+            continue;
+        }
+        let width = run.cells.len() as u64;
+        run.repeat -= remaining / width;
+        let part = (remaining % width) as usize;
+        if part == 0 {
+            return run.cells[run.cells.len() - 1];
+        }
+        // Part of a block is taken, and the rest of it is left closest to the head
+        let cells = run.cells.clone();
+        run.repeat -= 1;
+        if run.repeat == 0 {
+            runs.pop();
+        }
+        push(runs, &cells[part..], 1);
+        return cells[part - 1];
+    }
+    on
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::execution::TestCaseExecution;
+    use crate::level::{TestCase, Topology};
+    use crate::levels;
+    use crate::macro_execution::MacroExecution;
+    use crate::program::{Card, Direction, Instruction, Program};
+    use crate::programs;
+    use crate::random::Random;
+
+    fn instruction(random: &mut Random, cards: usize) -> Instruction {
+        Instruction {
+            write_symbol: [None, Some(false), Some(true)][random.next(3) as usize],
+            move_direction: [None, Some(Direction::Left), Some(Direction::Right)][random.next(3) as usize],
+            next_card: Some(random.next(cards as u64 + 1) as usize).filter(|&card| card < cards),
+            other_tapes: Vec::new(),
+        }
+    }
+
+    fn program(random: &mut Random, cards: usize) -> Program {
+        Program {
+            name: "random".to_string(),
+            description: String::new(),
+            initial_card: 0,
+            cards: (0..cards)
+                .map(|index| Card {
+                    name: index.to_string(),
+                    tape_on: instruction(random, cards),
+                    tape_off: instruction(random, cards),
+                    other_instructions: Vec::new(),
+                    alternatives: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn assert_same(tc: TestCase, program: Program, max_steps: u64) {
+        let mut naive = TestCaseExecution::new(tc.clone(), program.clone());
+        let mut fast = MacroExecution::new(tc, program);
+        let naive_terminated = naive.run(max_steps);
+        let fast_terminated = fast.run(max_steps);
+        assert_eq!(fast_terminated, naive_terminated);
+        assert_eq!(fast.steps, naive.steps);
+        assert_eq!(fast.get_current_position(), naive.get_current_position());
+        assert_eq!(fast.is_terminated(), naive.is_terminated());
+        assert_eq!(fast.get_tape(), naive.positions_on);
+        assert_eq!(fast.count_lit(), naive.positions_on.len() as u64);
+    }
+
+    #[test]
+    fn same_as_naive_for_solutions() {
+        for level in levels::builtins() {
+            if let Some(solution) = &level.solution {
+                let program = programs::builtins().into_iter().find(|program| &program.name == solution).unwrap();
+                if program.tapes() > 1 {
+                    continue;
+                }
+                for tc in level.cases.into_iter().filter(|tc| tc.topology == Topology::Line) {
+                    assert_same(tc, program.clone(), 10_000);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_naive_for_random_programs() {
+        let mut random = Random::new(0x2545f4914f6cdd1d);
+        for _ in 0..2000 {
+            let cards = 1 + random.next(4) as usize;
+            let program = program(&mut random, cards);
+            let tc = TestCase {
+                initial_tape: random.tape(8),
+                ..TestCase::default()
+            };
+            let max_steps = random.next(300);
+            assert_same(tc, program, max_steps);
+        }
+    }
+
+    #[test]
+    fn sweeps_are_single_steps() {
+        let program = programs::builtins().into_iter().find(|program| program.name == "light_right").unwrap();
+        let mut execution = MacroExecution::new(TestCase::default(), program);
+        execution.step(1_000_000_000);
+        assert_eq!(execution.steps, 1_000_000_000);
+        assert_eq!(execution.get_current_position(), 1_000_000_000);
+        assert_eq!(execution.count_lit(), 1_000_000_000);
+    }
+
+    #[test]
+    fn same_as_naive_for_long_runs_of_random_programs() {
+        let mut random = Random::new(0x9e3779b97f4a7c15);
+        for _ in 0..300 {
+            let cards = 2 + random.next(4) as usize;
+            let program = program(&mut random, cards);
+            let tc = TestCase {
+                initial_tape: random.tape(4),
+                ..TestCase::default()
+            };
+            let max_steps = random.next(20_000);
+            assert_same(tc, program, max_steps);
+        }
+    }
+
+    fn go(write_symbol: Option<bool>, move_direction: Option<Direction>, next_card: Option<usize>) -> Instruction {
+        Instruction { write_symbol, move_direction, next_card, other_tapes: Vec::new() }
+    }
+
+    fn cards(instructions: Vec<(Instruction, Instruction)>) -> Program {
+        Program {
+            name: "cards".to_string(),
+            description: String::new(),
+            initial_card: 0,
+            cards: instructions.into_iter()
+                .enumerate()
+                .map(|(index, (tape_off, tape_on))| Card {
+                    name: index.to_string(),
+                    tape_on,
+                    tape_off,
+                    other_instructions: Vec::new(),
+                    alternatives: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Lights every other cell of a region that grows by one lit cell on each side every time it
+    /// goes over it, with two cards for each direction.
+    fn bouncer() -> Program {
+        let halt = go(None, None, None);
+        cards(vec![
+            (go(Some(true), Some(Direction::Right), Some(2)), go(None, Some(Direction::Right), Some(1))),
+            (go(None, Some(Direction::Right), Some(0)), halt.clone()),
+            (go(None, Some(Direction::Left), Some(3)), halt.clone()),
+            (go(Some(true), Some(Direction::Left), Some(5)), go(None, Some(Direction::Left), Some(4))),
+            (go(None, Some(Direction::Left), Some(3)), halt.clone()),
+            (go(None, Some(Direction::Right), Some(0)), halt),
+        ])
+    }
+
+    #[test]
+    fn sweeps_of_several_cards_are_single_steps() {
+        let program = cards(vec![
+            (go(Some(true), Some(Direction::Right), Some(1)), go(None, None, None)),
+            (go(None, Some(Direction::Right), Some(0)), go(None, None, None)),
+        ]);
+        let mut execution = MacroExecution::new(TestCase::default(), program);
+        execution.step(1_000_000_000);
+        assert_eq!(execution.steps, 1_000_000_000);
+        assert_eq!(execution.get_current_position(), 1_000_000_000);
+        assert_eq!(execution.count_lit(), 500_000_000);
+    }
+
+    #[test]
+    fn going_back_and_forth_takes_a_few_steps_per_sweep() {
+        assert_same(TestCase::default(), bouncer(), 300_000);
+
+        let mut execution = MacroExecution::new(TestCase::default(), bouncer());
+        let mut macro_steps = 0;
+        while execution.steps < 10_000_000_000 {
+            execution.step(10_000_000_000 - execution.steps);
+            macro_steps += 1;
+        }
+        assert_eq!(execution.steps, 10_000_000_000);
+        // Going over a region of n cells takes n steps, so there are about a hundred thousand sweeps
+        assert!(macro_steps < 1_000_000, "{}", macro_steps);
+    }
+
+    #[test]
+    fn far_away_cells_are_read_at_once() {
+        let tc = TestCase {
+            initial_tape: HashSet::from([-1_000_000_000_000_000, -3, 0, 2, 1_000_000_000_000_000]),
+            ..TestCase::default()
+        };
+        let light_everything = cards(vec![(go(Some(true), Some(Direction::Right), Some(0)), go(None, Some(Direction::Right), Some(0)))]);
+        let mut execution = MacroExecution::new(tc, light_everything);
+        assert_eq!(execution.count_lit(), 5);
+        execution.run(2_000_000_000_000_000);
+        assert_eq!(execution.get_current_position(), 2_000_000_000_000_000);
+        assert_eq!(execution.count_lit(), 2_000_000_000_000_002);
+
+        let near = TestCase {
+            initial_tape: HashSet::from([-1000, -3, 0, 2, 999]),
+            ..TestCase::default()
+        };
+        assert_same(near, bouncer(), 100_000);
+    }
+
+    #[test]
+    fn the_head_falls_off_the_end_of_the_tape() {
+        let program = programs::builtins().into_iter().find(|program| program.name == "light_right").unwrap();
+        let mut execution = MacroExecution::new(TestCase::default(), program);
+        assert!(execution.run(u64::MAX));
+        assert!(execution.fell_off());
+        assert_eq!(execution.get_halted_on(), None);
+        assert_eq!(execution.steps, i64::MAX.unsigned_abs() + 1);
+        assert_eq!(execution.get_current_position(), i64::MAX);
+    }
+}
@@ -2,34 +2,33 @@
 
 use std::{fs, process, thread};
 use std::cmp::max;
-use std::fs::{create_dir_all, File, read_dir};
+use std::fs::{File, read_dir};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::Error;
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use console::Term;
-use directories::ProjectDirs;
-use prettytable::{row, Table};
-
-use crate::execution::LevelExecution;
-use crate::level::Level;
-use crate::level_dto::LevelDto;
-use crate::program::Program;
-use crate::program_dto::ProgramDto;
-use crate::render::{render, render_tce};
-
-mod execution;
-mod level;
-mod level_dto;
-mod levels;
-mod program;
-mod render;
-mod program_dto;
-mod programs;
-mod outcome;
+use prettytable::{row, Cell, Table};
+
+use tur::{beaver, coverage, decider, equivalence, loader, minimise, nondeterminism, packs, parallel, profile, program_text, programs, synthesis};
+use tur::breakpoint::Breakpoints;
+use tur::data_dir::DataDir;
+use tur::equivalence::Ending;
+use tur::execution::{LevelExecution, TestCaseExecutionState};
+use tur::level::{Level, TestCase, Topology};
+use tur::level_dto::LevelDto;
+use tur::lint::{lint, Severity};
+use tur::macro_execution::MacroExecution;
+use tur::nondeterminism::{Choice, SearchOutcome};
+use tur::profile::Profile;
+use tur::program::Program;
+use tur::program_dto::ProgramDto;
+use tur::random::Random;
+use tur::render::TapeView;
+use tur::tui::Tui;
 
 #[derive(Parser)]
 struct Cli {
@@ -59,6 +58,173 @@ enum Command {
         #[command(subcommand)]
         resource: EditResource,
     },
+    /// Install things
+    Install {
+        #[command(subcommand)]
+        resource: InstallResource,
+    },
+    /// Reveal the next hint for a level
+    Hint {
+        /// Name of level
+        level: String,
+    },
+    /// Inspect a program for problems without running it
+    Lint {
+        /// Name of program to inspect
+        program: String,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Runs a program on a level as fast as possible, without showing each step
+    Run {
+        /// Name of program to run
+        program: String,
+
+        /// Name of level to run on
+        level: String,
+
+        /// Maximum amount of steps per test case
+        #[arg(short, long, default_value_t = 1_000_000_000)]
+        max_steps: u64,
+        /// Amount of threads to use, one per processor by default
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+    /// Shows how often each card of a program fires on a level, and how the head moves
+    Profile {
+        /// Name of program to profile
+        program: String,
+
+        /// Name of level to run on
+        level: String,
+
+        /// Maximum amount of steps per test case
+        #[arg(short, long, default_value_t = 1_000_000)]
+        max_steps: u64,
+    },
+    /// Explores every choice of a nondeterministic program breadth-first, showing for each test
+    /// case whether a branch solves it and the choices that branch took
+    Explore {
+        /// Name of program to explore
+        program: String,
+
+        /// Name of level to explore on
+        level: String,
+
+        /// Maximum amount of steps per branch
+        #[arg(short, long, default_value_t = 10_000)]
+        max_steps: u64,
+
+        /// Maximum amount of branches running at once per test case
+        #[arg(short = 'b', long, default_value_t = nondeterminism::DEFAULT_MAX_BRANCHES)]
+        max_branches: usize,
+    },
+    /// Shows which card instructions of a program the test cases of a level execute
+    Coverage {
+        /// Name of program to run
+        program: String,
+
+        /// Name of level to run on
+        level: String,
+
+        /// Maximum amount of steps per test case
+        #[arg(short, long, default_value_t = 1_000_000)]
+        max_steps: u64,
+
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Compare what two programs do, and how they are built
+    DiffBehaviour {
+        /// Name of first program
+        a: String,
+
+        /// Name of second program
+        b: String,
+
+        /// (Optional) Name of level whose test cases are compared first
+        #[arg(short, long)]
+        level: Option<String>,
+
+        /// Generated tapes have lit cells up to this distance from the start
+        #[arg(short, long, default_value_t = 8)]
+        radius: i64,
+
+        /// Amount of generated tapes
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+
+        /// Maximum amount of steps per input
+        #[arg(short, long, default_value_t = 100_000)]
+        max_steps: u64,
+    },
+    /// Remove unreachable cards and merge cards that behave the same
+    Minimise {
+        /// Name of program to minimise
+        program: String,
+
+        /// (Optional) Name of level the minimised program is checked against
+        #[arg(short, long)]
+        level: Option<String>,
+    },
+    /// Search for programs that solve a level
+    Solve {
+        /// Name of level to solve
+        level: String,
+
+        /// Maximum amount of cards in a program
+        #[arg(short = 'c', long, default_value_t = 2)]
+        max_cards: usize,
+
+        /// Maximum amount of steps per test case
+        #[arg(short, long, default_value_t = 1000)]
+        max_steps: u64,
+        /// Amount of threads to use, one per processor by default
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+    /// Check that programs solve levels, running them in parallel
+    Test {
+        /// Names of levels to check, every level with a solution if none are given
+        levels: Vec<String>,
+
+        /// Name of program to run on every level, instead of the solution of each level
+        #[arg(short, long)]
+        program: Option<String>,
+
+        /// Maximum amount of steps per test case
+        #[arg(short, long, default_value_t = 1_000_000)]
+        max_steps: u64,
+
+        /// Amount of threads to use, one per processor by default
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+    /// Search for the busy beaver: the halting program with the most steps or lit cells
+    Beaver {
+        /// Amount of cards in a program
+        #[arg(long, default_value_t = 2)]
+        states: usize,
+
+        /// Amount of tape symbols, only 2 is supported
+        #[arg(long, default_value_t = 2)]
+        symbols: usize,
+
+        /// Maximum amount of steps before a program is considered not to halt
+        #[arg(short, long, default_value_t = 1000)]
+        max_steps: u64,
+
+        /// (Optional) File to write the undecided programs to
+        #[arg(short, long)]
+        export: Option<PathBuf>,
+    },
+    /// Check that things are correct
+    Validate {
+        #[command(subcommand)]
+        resource: ValidateResource,
+    },
     /// Executes a program on a level
     Exec {
         /// Name of program to execute
@@ -74,21 +240,58 @@ enum Command {
         /// Test case to start with
         #[arg(short, long, default_value_t = 0)]
         test_case: usize,
+
+        /// Scroll the tape with every step to keep the head in the middle
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Never show the tape before this position
+        #[arg(long, allow_hyphen_values = true)]
+        from: Option<i64>,
+
+        /// Never show the tape after this position
+        #[arg(long, allow_hyphen_values = true)]
+        to: Option<i64>,
+
+        /// Skip ahead without drawing until the program changes to this card, can be repeated
+        #[arg(short, long = "break-at")]
+        break_at: Vec<String>,
+
+        /// Maximum amount of steps per test case when skipping ahead to a breakpoint
+        #[arg(short, long, default_value_t = 1_000_000_000)]
+        max_steps: u64,
     },
 }
 
+#[derive(Copy, Clone, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum ProgramFormat {
+    Yaml,
+    Tur,
+}
+
 #[derive(Subcommand)]
 enum GetResource {
     /// Get all or specific program
     Program {
         /// (Optional) Name of program
         name: Option<String>,
+        /// Format to print a specific program in
+        #[arg(short, long, value_enum, default_value_t = ProgramFormat::Yaml)]
+        format: ProgramFormat,
     },
     /// Get all or specific program
     Level {
         /// (Optional) Name of level
         name: Option<String>,
     },
+    /// Get all level packs
+    Pack,
 }
 
 #[derive(Subcommand)]
@@ -118,6 +321,28 @@ enum EditResource {
     },
 }
 
+#[derive(Subcommand)]
+enum InstallResource {
+    /// Install a level pack from a directory or tarball containing a pack.yaml
+    Pack {
+        /// Path to the directory or tarball
+        path: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ValidateResource {
+    /// Run the reference solution of a level through all of its test cases
+    Level {
+        /// Name of level
+        name: String,
+
+        /// Maximum amount of steps per test case
+        #[arg(short, long, default_value_t = 100_000)]
+        max_steps: u64,
+    },
+}
+
 #[derive(Subcommand)]
 enum LevelCommand {
     List,
@@ -127,11 +352,25 @@ fn main() {
     let cli = Cli::parse();
     let result = match cli.command {
         Command::Get { resource } => get_resource(&resource),
-        Command::Exec { program, level, sleep, test_case } =>
-            run(&program, &level, sleep, test_case),
+        Command::Exec { program, level, sleep, test_case, follow, from, to, break_at, max_steps } =>
+            run(&program, &level, sleep, test_case, TapeView { follow, from, to }, &break_at, max_steps),
         Command::Create { resource } => create_resource(&resource),
         Command::Delete { resource } => delete_resource(&resource),
         Command::Edit { resource } => edit_resource(&resource),
+        Command::Validate { resource } => validate_resource(&resource),
+        Command::Install { resource } => install_resource(&resource),
+        Command::Hint { level } => hint(&level),
+        Command::Lint { program, format } => lint_program(&program, format),
+        Command::Run { program, level, max_steps, jobs } => run_fast(&program, &level, max_steps, jobs),
+        Command::Profile { program, level, max_steps } => profile_program(&program, &level, max_steps),
+        Command::Explore { program, level, max_steps, max_branches } => explore(&program, &level, max_steps, max_branches),
+        Command::Coverage { program, level, max_steps, format } => coverage_report(&program, &level, max_steps, format),
+        Command::DiffBehaviour { a, b, level, radius, samples, max_steps } =>
+            diff_behaviour(&a, &b, level.as_deref(), radius, samples, max_steps),
+        Command::Minimise { program, level } => minimise_program(&program, level.as_deref()),
+        Command::Solve { level, max_cards, max_steps, jobs } => solve_level(&level, max_cards, max_steps, jobs),
+        Command::Test { levels, program, max_steps, jobs } => test_levels(&levels, program.as_deref(), max_steps, jobs),
+        Command::Beaver { states, symbols, max_steps, export } => busy_beaver(states, symbols, max_steps, export.as_deref()),
     };
 
     if let Err(err) = result {
@@ -157,32 +396,50 @@ fn delete_resource(resource: &DeleteResource) -> Result<()> {
     }
 }
 
+fn install_resource(resource: &InstallResource) -> Result<()> {
+    match resource {
+        InstallResource::Pack { path } => install_pack(path)
+    }
+}
+
+fn validate_resource(resource: &ValidateResource) -> Result<()> {
+    match resource {
+        ValidateResource::Level { name, max_steps } => validate_level(name, *max_steps)
+    }
+}
+
 fn get_resource(resource: &GetResource) -> Result<()> {
     match resource {
-        GetResource::Program { name } =>
+        GetResource::Program { name, format } =>
             match name {
                 None => program_list(),
-                Some(name) => get_program(name),
+                Some(name) => get_program(name, *format),
             },
         GetResource::Level { name } =>
             match name {
                 None => level_list(),
                 Some(name) => get_level(name)
             }
+        GetResource::Pack => pack_list(),
     }
 }
 
-fn get_program(program_name: &str) -> Result<()> {
-    let program = find_program(program_name)?
+fn get_program(program_name: &str, format: ProgramFormat) -> Result<()> {
+    let data = DataDir::user()?;
+    let program = data.find_program(program_name)?
         .ok_or(Error::msg(format!("Program {} not found", program_name)))?;
     let dto = ProgramDto::from(program);
-    let serialized = serde_yaml::to_string(&dto)?;
+    let serialized = match format {
+        ProgramFormat::Yaml => serde_yaml::to_string(&dto)?,
+        ProgramFormat::Tur => program_text::print(&dto),
+    };
     Term::stdout().write_line(&serialized)?;
     Ok(())
 }
 
 fn get_level(level_name: &str) -> Result<()> {
-    let level = find_level(level_name)
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
         .ok_or(Error::msg(format!("Level {} not found", level_name)))?;
     let dto = LevelDto::from(level);
     let serialized = serde_yaml::to_string(&dto)?;
@@ -190,10 +447,172 @@ fn get_level(level_name: &str) -> Result<()> {
     Ok(())
 }
 
+fn validate_level(level_name: &str, max_steps: u64) -> Result<()> {
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
+        .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    let solution_name = level.solution.clone()
+        .ok_or_else(|| Error::msg(format!("Level {} has no solution", level_name)))?;
+    let program = data.find_program(&solution_name)?
+        .ok_or_else(|| Error::msg(format!("Solution {} of level {} not found", solution_name, level_name)))?;
+    let mut execution = LevelExecution::new(level, program);
+    execution.run(max_steps);
+
+    let mut table = Table::new();
+    table.set_titles(row!["Case", "Status", "Steps"]);
+    for (index, tce) in execution.executions.iter().enumerate() {
+        let status = match tce.get_state() {
+            TestCaseExecutionState::Pending | TestCaseExecutionState::Running =>
+                format!("Did not halt within {} steps", max_steps),
+            TestCaseExecutionState::Success => "Success".to_string(),
+            TestCaseExecutionState::Failure { errors } => errors.join("\n"),
+        };
+        table.add_row(row![index, status, tce.steps]);
+    }
+    Term::stdout().write_line(&table.to_string())?;
+
+    if execution.is_solved() {
+        Term::stdout().write_line(&format!("Level {} is solved by {}", level_name, solution_name))?;
+        Ok(())
+    } else {
+        Err(Error::msg(format!("Level {} is not solved by {}", level_name, solution_name)))
+    }
+}
+
+fn hint(level_name: &str) -> Result<()> {
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
+        .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    if level.hints.is_empty() {
+        return Err(Error::msg(format!("Level {} has no hints", level_name)));
+    }
+    let mut progress = data.load_progress()?;
+    let hints_used = progress.use_hint(level_name, level.hints.len());
+    data.save_progress(&progress)?;
+    let term = Term::stdout();
+    for (index, hint) in level.hints.iter().take(hints_used).enumerate() {
+        term.write_line(&format!("Hint {}/{}: {}", index + 1, level.hints.len(), hint))?;
+    }
+    if hints_used == level.hints.len() {
+        term.write_line("There are no more hints for this level")?;
+    }
+    Ok(())
+}
+
+fn lint_program(program_name: &str, format: OutputFormat) -> Result<()> {
+    let data = DataDir::user()?;
+    let program = data.find_program(program_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+    let diagnostics = lint(&program);
+    let term = Term::stdout();
+    match format {
+        OutputFormat::Json => term.write_line(&serde_json::to_string_pretty(&diagnostics)?)?,
+        OutputFormat::Text if diagnostics.is_empty() => term.write_line("No problems found")?,
+        OutputFormat::Text => {
+            let mut table = Table::new();
+            table.set_titles(row!["Severity", "Code", "Card", "Message"]);
+            for diagnostic in &diagnostics {
+                let severity = format!("{:?}", diagnostic.severity).to_lowercase();
+                table.add_row(row![severity, diagnostic.code, diagnostic.card.clone().unwrap_or_default(), diagnostic.message]);
+            }
+            term.write_line(&table.to_string())?;
+        }
+    }
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count();
+    if errors > 0 {
+        return Err(Error::msg(format!("Program {} has {} errors", program_name, errors)));
+    }
+    Ok(())
+}
+
+fn solve_level(level_name: &str, max_cards: usize, max_steps: u64, jobs: Option<usize>) -> Result<()> {
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
+        .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    let jobs = jobs.unwrap_or_else(parallel::default_jobs);
+    let solutions = synthesis::solve_parallel(&level, max_cards, max_steps, jobs);
+    let term = Term::stdout();
+    let smallest = solutions.iter()
+        .min_by_key(|solution| (solution.program.cards.len(), solution.steps))
+        .ok_or_else(|| Error::msg(format!("No programs with at most {} cards solve level {}", max_cards, level_name)))?;
+    let fastest = solutions.iter()
+        .min_by_key(|solution| (solution.steps, solution.program.cards.len()))
+        .unwrap();
+    term.write_line(&format!("Found {} solutions", solutions.len()))?;
+
+    let mut table = Table::new();
+    table.set_titles(row!["Program", "Cards", "Steps"]);
+    let mut found = vec![("smallest", smallest)];
+    if !std::ptr::eq(smallest, fastest) {
+        found.push(("fastest", fastest));
+    }
+    for (suffix, solution) in found {
+        let name = format!("{}_{}", level_name, suffix);
+        let file_path = data.program_file(&name)?;
+        if data.existing_program_file(&name)?.is_some() {
+            term.write_line(&format!("Program {} already exists, not overwriting it", name))?;
+            continue;
+        }
+        let mut program = solution.program.clone();
+        program.name = name.clone();
+        program.description = format!("Solves {} in {} steps, found by tur solve", level_name, solution.steps);
+        fs::write(file_path, serde_yaml::to_string(&ProgramDto::from(program))?)?;
+        table.add_row(row![name, solution.program.cards.len(), solution.steps]);
+    }
+    if !table.is_empty() {
+        term.write_line(&table.to_string())?;
+    }
+    Ok(())
+}
+
+fn busy_beaver(states: usize, symbols: usize, max_steps: u64, export: Option<&Path>) -> Result<()> {
+    if symbols != 2 {
+        return Err(Error::msg("Only 2 symbols are supported"));
+    }
+    if states == 0 {
+        return Err(Error::msg("There must be at least one state"));
+    }
+    let report = beaver::enumerate(states, &decider::builtins(max_steps));
+    let term = Term::stdout();
+    term.write_line(&format!("Enumerated {} programs with {} cards", report.machines, states))?;
+
+    let mut table = Table::new();
+    table.set_titles(row!["Decider", "Programs"]);
+    for (name, count) in &report.decided {
+        table.add_row(row![name, count]);
+    }
+    table.add_row(row!["undecided", report.holdouts.len()]);
+    term.write_line(&table.to_string())?;
+
+    let mut table = Table::new();
+    table.set_titles(row!["Champion", "Program", "Steps", "Lit"]);
+    for (category, champion) in [("steps", &report.most_steps), ("lit", &report.most_lit)] {
+        if let Some(champion) = champion {
+            table.add_row(row![category, beaver::standard_notation(&champion.program), champion.steps, champion.lit]);
+        }
+    }
+    term.write_line(&table.to_string())?;
+
+    if let Some(path) = export {
+        let dtos: Vec<ProgramDto> = report.holdouts.into_iter()
+            .enumerate()
+            .map(|(index, mut program)| {
+                program.name = format!("holdout_{}", index);
+                program.description = beaver::standard_notation(&program);
+                ProgramDto::from(program)
+            })
+            .collect();
+        fs::write(path, serde_yaml::to_string(&dtos)?)?;
+        term.write_line(&format!("Wrote {} undecided programs to {}", dtos.len(), path.to_string_lossy()))?;
+    }
+    Ok(())
+}
+
 fn program_create(name: &str) -> Result<()> {
+    let data = DataDir::user()?;
     let term = Term::stdout();
-    let file_path = program_file(name)?;
-    if file_path.exists() {
+    let file_path = data.program_file(name)?;
+    if data.existing_program_file(name)?.is_some() {
         term.write_line(&format!("Program {} already exists", name))?;
         return Ok(());
     }
@@ -207,12 +626,12 @@ fn program_create(name: &str) -> Result<()> {
 }
 
 fn program_edit(name: &str) -> Result<()> {
+    let data = DataDir::user()?;
     let term = Term::stdout();
-    let file_path = program_file(name)?;
-    if !file_path.exists() {
+    let Some(file_path) = data.existing_program_file(name)? else {
         term.write_line(&format!("Program {} does not exist", name))?;
         return Ok(());
-    }
+    };
     process::Command::new("vim")
         .arg(&file_path)
         .spawn()?
@@ -221,33 +640,26 @@ fn program_edit(name: &str) -> Result<()> {
 }
 
 fn program_delete(name: &str) -> Result<()> {
+    let data = DataDir::user()?;
     let term = Term::stdout();
-    let file_path = program_file(name)?;
-    if !file_path.exists() {
+    let Some(file_path) = data.existing_program_file(name)? else {
         term.write_line(&format!("Program {} does not exist", name))?;
         return Ok(());
-    }
+    };
     fs::remove_file(file_path)?;
     term.write_line(&format!("Program {} deleted", name))?;
     Ok(())
 }
 
 fn program_list() -> Result<()> {
-    let dir = read_dir(program_dir()?)?;
+    let data = DataDir::user()?;
+    let dir = read_dir(data.program_dir()?)?;
     let mut table = Table::new();
     table.set_titles(row!("Name", "Status"));
     for entry in dir {
         let entry = entry?;
-        let file_name = entry.file_name().to_string_lossy().trim_end_matches(".yaml").to_string();
-        let file_contents = fs::read_to_string(entry.path())?;
-        let dto: ProgramDto = match serde_yaml::from_str(&file_contents) {
-            Ok(dto) => dto,
-            Err(err) => {
-                table.add_row(row![file_name, err.to_string()]);
-                continue;
-            }
-        };
-        let program: Program = match dto.try_into() {
+        let file_name = entry.path().file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let program = match loader::read_program(&entry.path()) {
             Ok(program) => program,
             Err(err) => {
                 table.add_row(row![file_name, err.to_string()]);
@@ -277,94 +689,420 @@ fn level(command: LevelCommand) -> Result<()> {
 }
 
 fn level_list() -> Result<()> {
-    let level_dir = level_dir()?;
-    let dir = read_dir(level_dir)?;
+    let data = DataDir::user()?;
+    let progress = data.load_progress()?;
     let mut table = Table::new();
-    table.set_titles(row!["Name", "Solved", "Type"]);
-    for level in levels::builtins() {
-        table.add_row(row![level.name, false, "builtin"]);
+    table.set_titles(row!["Pack", "Name", "Status", "Hints", "Requires", "Type"]);
+    let (installed, unreadable_packs) = data.installed_packs()?;
+    let packs = packs::builtins().into_iter().map(|pack| (pack, "builtin"))
+        .chain(installed.into_iter().map(|pack| (pack, "installed")));
+    for (pack, pack_type) in packs {
+        for entry in &pack.entries {
+            let status = if progress.is_solved(&entry.level.name) {
+                "solved"
+            } else if entry.is_unlocked(&progress) {
+                "unlocked"
+            } else {
+                "locked"
+            };
+            let hints = format!("{}/{}", progress.get(&entry.level.name).hints_used, entry.level.hints.len());
+            table.add_row(row![pack.name, entry.level.name, status, hints, entry.requires.join(", "), pack_type]);
+        }
     }
-    for entry in dir {
-        let file_contents = fs::read_to_string(entry?.path())?;
-        let dto: LevelDto = serde_yaml::from_str(&file_contents)?;
-        let level: Level = dto.into();
-        table.add_row(row![level.name, false, "custom"]);
+    for unreadable in unreadable_packs {
+        table.add_row(row![unreadable.name, "", unreadable.error, "", "", "installed"]);
+    }
+    let (levels, unreadable_levels) = data.custom_levels()?;
+    for level in levels {
+        let status = if progress.is_solved(&level.name) { "solved" } else { "unlocked" };
+        let hints = format!("{}/{}", progress.get(&level.name).hints_used, level.hints.len());
+        table.add_row(row!["", level.name, status, hints, "", "custom"]);
+    }
+    for unreadable in unreadable_levels {
+        table.add_row(row!["", unreadable.name, unreadable.error, "", "", "custom"]);
+    }
+    Term::stdout().write_line(&table.to_string())?;
+    Ok(())
+}
+
+fn pack_list() -> Result<()> {
+    let data = DataDir::user()?;
+    let mut table = Table::new();
+    table.set_titles(row!["Name", "Description", "Levels", "Type"]);
+    for pack in packs::builtins() {
+        table.add_row(row![pack.name, pack.description, pack.entries.len(), "builtin"]);
+    }
+    let (installed, unreadable_packs) = data.installed_packs()?;
+    for pack in installed {
+        table.add_row(row![pack.name, pack.description, pack.entries.len(), "installed"]);
+    }
+    for unreadable in unreadable_packs {
+        table.add_row(row![unreadable.name, unreadable.error, "", "installed"]);
     }
     Term::stdout().write_line(&table.to_string())?;
     Ok(())
 }
 
-fn run(program_name: &str, level_name: &str, sleep: u64, test_case_index: usize) -> Result<()> {
+fn install_pack(path: &Path) -> Result<()> {
+    let pack = DataDir::user()?.install_pack(path)?;
+    Term::stdout().write_line(&format!("Pack {} installed with {} levels", pack.name, pack.entries.len()))?;
+    Ok(())
+}
+
+fn run(program_name: &str, level_name: &str, sleep: u64, test_case_index: usize, view: TapeView, break_at: &[String], max_steps: u64) -> Result<()> {
+    let data = DataDir::user()?;
     let sleep_duration = Duration::from_millis(max(sleep, 10));
-    let mut level = find_level(level_name)
+    let mut level = data.find_level(level_name)?
         .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    let missing = data.missing_requirements(level_name, &data.load_progress()?)?;
+    if !missing.is_empty() {
+        return Err(Error::msg(format!("Level {} is locked, solve {} first", level_name, missing.join(", "))));
+    }
     level.cases = level.cases[test_case_index..].to_vec();
-    let program = find_program(program_name)?
+    let program = data.find_program(program_name)?
         .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+    let mut breakpoints = Breakpoints::on_cards(&program, break_at).map_err(Error::msg)?;
     let mut execution = LevelExecution::new(level, program);
     let term = Term::stdout();
-    render(&term, &execution)?;
+    let mut tui = Tui::new(term.clone(), &execution.program, view);
+    if !breakpoints.is_empty() {
+        tui.run_until(&mut execution, max_steps, &mut breakpoints);
+    }
+    tui.draw(&execution)?;
     while !execution.is_terminated() {
         thread::sleep(sleep_duration);
-        execution.step();
-        render(&term, &execution)?;
+        tui.step(&mut execution);
+        tui.draw(&execution)?;
+    }
+
+    if test_case_index == 0 && execution.is_solved() {
+        let mut progress = data.load_progress()?;
+        progress.set_solved(level_name);
+        data.save_progress(&progress)?;
+        term.write_line(&format!("Level {} solved!", level_name))?;
     }
 
     Ok(())
 }
 
-fn find_level(level_name: &str) -> Option<Level> {
-    levels::builtins().into_iter().find(|level| level.name == level_name)
+fn run_fast(program_name: &str, level_name: &str, max_steps: u64, jobs: Option<usize>) -> Result<()> {
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
+        .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    let program = data.find_program(program_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+    if program.tapes() > 1 {
+        return Err(Error::msg("Only programs with one tape can be run quickly, use exec instead"));
+    }
+    if program.is_nondeterministic() {
+        return Err(Error::msg("Nondeterministic programs cannot be run quickly, use explore instead"));
+    }
+    if level.cases.iter().any(|tc| tc.topology != Topology::Line) {
+        return Err(Error::msg("Only levels on a line can be run quickly, use exec instead"));
+    }
+    let jobs = jobs.unwrap_or_else(parallel::default_jobs);
+    let rows = parallel::map(level.cases, jobs, |tc| {
+        let target = tc.target.clone();
+        let mut execution = MacroExecution::new(tc, program.clone());
+        let status = if !execution.run(max_steps) {
+            format!("Did not halt within {} steps", max_steps)
+        } else if execution.fell_off() {
+            format!("Fell off the tape at position {}", execution.get_current_position())
+        } else {
+            let errors = target
+                .map(|target| target.get_errors(&execution.get_tape(), execution.get_current_position()))
+                .unwrap_or_default();
+            if errors.is_empty() { "Success".to_string() } else { errors.join("\n") }
+        };
+        (status, execution.steps, execution.get_current_position(), execution.count_lit())
+    });
+    let mut table = Table::new();
+    table.set_titles(row!["Case", "Status", "Steps", "Position", "Lit"]);
+    for (index, (status, steps, position, lit)) in rows.into_iter().enumerate() {
+        table.add_row(row![index, status, steps, position, lit]);
+    }
+    Term::stdout().write_line(&table.to_string())?;
+    Ok(())
 }
 
-fn find_program(name: &str) -> Result<Option<Program>> {
-    for entry in read_dir(program_dir()?)? {
-        let entry = entry?;
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        if file_name != format!("{}.yaml", name) {
-            continue;
-        }
-        let file_contents = fs::read_to_string(entry.path())?;
-        let dto: ProgramDto = serde_yaml::from_str(&file_contents)?;
-        let program = Program::try_from(dto)?;
-        return Ok(Some(program));
+fn test_levels(level_names: &[String], program_name: Option<&str>, max_steps: u64, jobs: Option<usize>) -> Result<()> {
+    let data = DataDir::user()?;
+    let levels = if level_names.is_empty() {
+        data.all_levels()?.into_iter()
+            .filter(|level| program_name.is_some() || level.solution.is_some())
+            .collect()
+    } else {
+        level_names.iter()
+            .map(|name| data.find_level(name)?.ok_or_else(|| Error::msg(format!("Level {} not found", name))))
+            .collect::<Result<Vec<Level>>>()?
+    };
+    let mut pairs = Vec::new();
+    for level in levels {
+        let program_name = program_name.or(level.solution.as_deref())
+            .ok_or_else(|| Error::msg(format!("Level {} has no solution", level.name)))?;
+        let program = data.find_program(program_name)?
+            .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+        pairs.push((level, program));
     }
-    for program in programs::builtins() {
-        if program.name == name {
-            return Ok(Some(program));
-        }
+
+    let executions = parallel::run_levels(pairs, max_steps, jobs.unwrap_or_else(parallel::default_jobs));
+    let mut table = Table::new();
+    table.set_titles(row!["Level", "Program", "Status", "Steps"]);
+    for execution in &executions {
+        let status = if execution.is_solved() {
+            "Solved".to_string()
+        } else if !execution.is_terminated() {
+            format!("Did not halt within {} steps", max_steps)
+        } else {
+            "Failed".to_string()
+        };
+        table.add_row(row![execution.level.name, execution.program.name, status, execution.get_steps()]);
     }
+    Term::stdout().write_line(&table.to_string())?;
+    let failed = executions.iter().filter(|execution| !execution.is_solved()).count();
+    if failed > 0 {
+        return Err(Error::msg(format!("{} of {} levels not solved", failed, executions.len())));
+    }
+    Ok(())
+}
 
-    let maybe_builtin = programs::builtins()
-        .into_iter()
-        .find(|program| program.name == name);
-    Ok(maybe_builtin)
+fn explore(program_name: &str, level_name: &str, max_steps: u64, max_branches: usize) -> Result<()> {
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
+        .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    let program = data.find_program(program_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+    let (execution, searches) = nondeterminism::explore(level, program, max_steps, max_branches);
+
+    let mut table = Table::new();
+    table.set_titles(row!["Case", "Result", "Steps", "Branches", "Choices"]);
+    for (index, (tce, search)) in execution.executions.iter().zip(&searches).enumerate() {
+        let result = match (search.outcome, tce.get_state()) {
+            (SearchOutcome::Accepted, _) => "Accepted".to_string(),
+            (SearchOutcome::Rejected, TestCaseExecutionState::Failure { errors }) => format!("Rejected\n{}", errors.join("\n")),
+            (SearchOutcome::Rejected, _) => "Rejected".to_string(),
+            (SearchOutcome::Exhausted, _) => format!("Gave up after {} steps or {} branches", max_steps, max_branches),
+        };
+        let choices = describe_path(&execution.program, &search.path);
+        table.add_row(row![index, result, tce.steps, search.branches, choices]);
+    }
+    Term::stdout().write_line(&table.to_string())?;
+    let accepted = searches.iter().filter(|search| search.outcome == SearchOutcome::Accepted).count();
+    if accepted < searches.len() {
+        return Err(Error::msg(format!("{} of {} test cases not accepted", searches.len() - accepted, searches.len())));
+    }
+    Ok(())
 }
 
-fn project_dirs() -> Result<ProjectDirs> {
-    ProjectDirs::from("org", "simonolander", "Tur").ok_or(Error::msg("Unable to find suitable project directory"))
+/// Describes the choices of a branch one card per line, as the card, what the heads read and the
+/// number of the choice, collapsing repeated choices into a count.
+fn describe_path(program: &Program, path: &[Choice]) -> String {
+    let tapes = program.tapes();
+    let mut lines: Vec<(Choice, usize)> = Vec::new();
+    for &choice in path {
+        match lines.last_mut() {
+            Some((last, count)) if *last == choice => *count += 1,
+            _ => lines.push((choice, 1)),
+        }
+    }
+    lines.iter()
+        .map(|(choice, count)| {
+            let read = (0..tapes).map(|tape| if choice.read >> tape & 1 == 1 { "on" } else { "off" }).collect::<Vec<_>>().join(", ");
+            let repeat = if *count > 1 { format!(" ×{}", count) } else { String::new() };
+            format!("{} {} #{}{}", program.cards[choice.card].name, read, choice.choice + 1, repeat)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-fn level_dir() -> Result<PathBuf> {
-    project_dirs().map(|dirs| dirs.data_dir().join("level"))
+fn profile_program(program_name: &str, level_name: &str, max_steps: u64) -> Result<()> {
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
+        .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    let program = data.find_program(program_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+    let (execution, profiles) = profile::profile(level, program, max_steps);
+    let mut total = Profile::new(&execution.program);
+    profiles.iter().for_each(|profile| total.merge(profile));
+
+    let mut table = Table::new();
+    table.set_titles(row!["Case", "Status", "Steps", "Writes", "Travel", "Leftmost", "Rightmost"]);
+    for (index, (tce, profile)) in execution.executions.iter().zip(&profiles).enumerate() {
+        let status = match tce.get_state() {
+            TestCaseExecutionState::Success => "Success".to_string(),
+            TestCaseExecutionState::Failure { errors } => errors.join("\n"),
+            _ => format!("Did not halt within {} steps", max_steps),
+        };
+        table.add_row(row![index, status, profile.steps, profile.writes, profile.travel, profile.leftmost, profile.rightmost]);
+    }
+    table.add_row(row!["Total", "", total.steps, total.writes, total.travel, total.leftmost, total.rightmost]);
+    let term = Term::stdout();
+    term.write_line(&table.to_string())?;
+
+    let mut table = Table::new();
+    let mut titles = row!["Card", "Tape"];
+    (0..profiles.len()).for_each(|index| titles.add_cell(Cell::new(&index.to_string())));
+    titles.add_cell(Cell::new("Total"));
+    titles.add_cell(Cell::new("Share"));
+    table.set_titles(titles);
+    let tapes = execution.program.tapes();
+    for (card_index, card) in execution.program.cards.iter().enumerate() {
+        let count = |profile: &Profile, instruction: Option<usize>| match instruction {
+            None => profile.card_count(card_index),
+            Some(instruction) => profile.instructions[card_index][instruction],
+        };
+        let instructions = card.all_instructions().enumerate()
+            .map(|(index, (read, choice, _))| (String::new(), describe_read(read, tapes, choice), Some(index)));
+        for (name, tape, instruction) in std::iter::once((card.name.clone(), String::new(), None)).chain(instructions) {
+            let mut row = row![name, tape];
+            profiles.iter().for_each(|profile| row.add_cell(Cell::new(&count(profile, instruction).to_string())));
+            row.add_cell(Cell::new(&count(&total, instruction).to_string()));
+            let share = if total.steps == 0 { 0.0 } else { 100.0 * count(&total, instruction) as f64 / total.steps as f64 };
+            row.add_cell(Cell::new(&format!("{:.1}%", share)));
+            table.add_row(row);
+        }
+    }
+    term.write_line(&table.to_string())?;
+    Ok(())
 }
 
-fn program_dir() -> Result<PathBuf> {
-    let buf = project_dirs().map(|dirs| dirs.data_dir().join("program"))?;
-    if !buf.exists() {
-        create_dir_all(&buf)?;
+/// What the heads read for an instruction, as on or off for every tape, with the number of the
+/// choice for the alternatives of nondeterministic cards
+fn describe_read(read: usize, tapes: usize, choice: usize) -> String {
+    let symbols = (0..tapes).map(|tape| if read >> tape & 1 == 1 { "on" } else { "off" }).collect::<Vec<_>>().join(", ");
+    match choice {
+        0 => symbols,
+        _ => format!("{} #{}", symbols, choice + 1),
     }
-    Ok(buf)
 }
 
-fn program_file(name: &str) -> Result<PathBuf> {
-    let dir = program_dir()?;
-    let path = dir.join(format!("{}.yaml", name));
-    let parent = path.parent()
-        .ok_or(Error::msg(format!("Could not get parent of path {}", path.to_string_lossy())))?;
-    if parent == dir.as_path() {
-        Ok(path)
+fn coverage_report(program_name: &str, level_name: &str, max_steps: u64, format: OutputFormat) -> Result<()> {
+    let data = DataDir::user()?;
+    let level = data.find_level(level_name)?
+        .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+    let program = data.find_program(program_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+    let coverage = coverage::coverage(level, program, max_steps);
+    let term = Term::stdout();
+    if let OutputFormat::Json = format {
+        term.write_line(&serde_json::to_string_pretty(&coverage)?)?;
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_titles(row!["Card", "Tape", "Cases", "Executions"]);
+    for instruction in &coverage.instructions {
+        let read = instruction.read.iter().enumerate().fold(0, |read, (tape, &on)| read | (on as usize) << tape);
+        let tape = describe_read(read, instruction.read.len(), instruction.choice);
+        let cases = instruction.cases.iter().map(usize::to_string).collect::<Vec<_>>().join(", ");
+        if instruction.is_covered() {
+            table.add_row(row![instruction.card, tape, cases, instruction.executions]);
+        } else {
+            table.add_row(row![Fr->instruction.card, Fr->tape, Fr->"never executed", Fr->instruction.executions]);
+        }
+    }
+    term.write_line(&table.to_string())?;
+    term.write_line(&format!("{} of {} instructions covered", coverage.covered_count(), coverage.instructions.len()))?;
+    if !coverage.unterminated.is_empty() {
+        let cases = coverage.unterminated.iter().map(usize::to_string).collect::<Vec<_>>().join(", ");
+        term.write_line(&format!("Test cases {} did not halt within {} steps", cases, max_steps))?;
+    }
+    Ok(())
+}
+
+fn diff_behaviour(a_name: &str, b_name: &str, level_name: Option<&str>, radius: i64, samples: usize, max_steps: u64) -> Result<()> {
+    let data = DataDir::user()?;
+    let a = data.find_program(a_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", a_name)))?;
+    let b = data.find_program(b_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", b_name)))?;
+    if a.tapes() > 1 || b.tapes() > 1 {
+        return Err(Error::msg("Only the behaviour of programs with one tape can be compared"));
+    }
+    if a.is_nondeterministic() || b.is_nondeterministic() {
+        return Err(Error::msg("Only the behaviour of deterministic programs can be compared"));
+    }
+    let mut inputs = match level_name {
+        Some(level_name) => data.find_level(level_name)?
+            .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?
+            .cases,
+        None => Vec::new(),
+    };
+    if inputs.iter().any(|tc| tc.topology != Topology::Line) {
+        return Err(Error::msg("Only the behaviour on levels on a line can be compared"));
+    }
+    inputs.push(TestCase::default());
+    let mut random = Random::new(samples as u64);
+    inputs.extend((0..samples).map(|_| TestCase { initial_tape: random.tape(radius), ..TestCase::default() }));
+    let input_count = inputs.len();
+
+    let term = Term::stdout();
+    let structural = equivalence::structural_diff(&a, &b);
+    if structural.is_isomorphic() {
+        term.write_line("The programs are the same up to renaming of cards")?;
     } else {
-        Err(Error::msg(format!("Mismatched parents: expected {} but was {}", dir.to_string_lossy(), parent.to_string_lossy())))
+        term.write_line("The programs are built differently:")?;
+        for difference in &structural.differences {
+            term.write_line(&format!("  - {}", difference))?;
+        }
     }
-}
\ No newline at end of file
+    let mut table = Table::new();
+    table.set_titles(row![a_name, b_name]);
+    for (name_a, name_b) in &structural.matched {
+        table.add_row(row![name_a, name_b]);
+    }
+    term.write_line(&table.to_string())?;
+
+    match equivalence::behaviour_difference(&a, &b, inputs, max_steps) {
+        None => term.write_line(&format!("No difference in behaviour found on {} inputs", input_count))?,
+        Some(difference) => {
+            let describe = |ending: &Ending| match ending {
+                Ending::Halted { position, tape } => format!("halts at position {} with tape {:?}", position, tape),
+                Ending::FellOff { position } => format!("falls off the tape at position {}", position),
+                Ending::Running => format!("does not halt within {} steps", max_steps),
+            };
+            term.write_line(&format!("The programs behave differently on tape {:?}:", difference.input))?;
+            term.write_line(&format!("  - {} {}", a_name, describe(&difference.a)))?;
+            term.write_line(&format!("  - {} {}", b_name, describe(&difference.b)))?;
+        }
+    }
+    Ok(())
+}
+
+fn minimise_program(program_name: &str, level_name: Option<&str>) -> Result<()> {
+    let data = DataDir::user()?;
+    let program = data.find_program(program_name)?
+        .ok_or_else(|| Error::msg(format!("Program {} not found", program_name)))?;
+    let minimisation = minimise::minimise(&program);
+    let term = Term::stdout();
+    for name in &minimisation.unreachable {
+        term.write_line(&format!("Removed unreachable card {}", name))?;
+    }
+    for (name, into) in &minimisation.merged {
+        term.write_line(&format!("Merged card {} into {}", name, into))?;
+    }
+    term.write_line(&format!("{} cards left of {}", minimisation.program.cards.len(), program.cards.len()))?;
+
+    if let Some(level_name) = level_name {
+        let level = data.find_level(level_name)?
+            .ok_or_else(|| Error::msg(format!("Level {} not found", level_name)))?;
+        let mut execution = LevelExecution::new(level, minimisation.program.clone());
+        execution.run(100_000);
+        if execution.is_solved() {
+            term.write_line(&format!("The minimised program solves level {}", level_name))?;
+        } else {
+            return Err(Error::msg(format!("The minimised program does not solve level {}, not saving it", level_name)));
+        }
+    }
+
+    let name = format!("{}_minimised", program_name);
+    let file_path = data.program_file(&name)?;
+    if data.existing_program_file(&name)?.is_some() {
+        return Err(Error::msg(format!("Program {} already exists, not overwriting it", name)));
+    }
+    let mut minimised = minimisation.program;
+    minimised.name = name.clone();
+    fs::write(file_path, serde_yaml::to_string(&ProgramDto::from(minimised))?)?;
+    term.write_line(&format!("Saved as program {}", name))?;
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::collections::{HashMap, VecDeque};
+use std::iter;
+
+use crate::program::{Alternative, Card, Direction, Instruction, Program};
+
+pub struct Minimisation {
+    pub program: Program,
+    /// Names of cards that cannot be reached from the initial card
+    pub unreachable: Vec<String>,
+    /// Names of cards that were merged into another card, and the name of that card
+    pub merged: Vec<(String, String)>,
+}
+
+/// Removes unreachable cards, merges cards that behave identically, and orders the remaining
+/// cards by when they are first reached from the initial card.
+///
+/// Cards are merged by repeatedly splitting groups of cards: first by what their instructions
+/// write and how they move, then by which groups their next cards are in, until no group splits.
+/// Alternatives of nondeterministic cards count like the other instructions, in their order.
+pub fn minimise(program: &Program) -> Minimisation {
+    let order = reachable_in_order(program, |index| program.cards[index].next_cards().collect());
+    let unreachable = program.cards.iter()
+        .enumerate()
+        .filter(|(index, _)| !order.contains(index))
+        .map(|(_, card)| card.name.clone())
+        .collect();
+
+    let mut groups: HashMap<usize, usize> = group_by(&order, |index| {
+        let card = &program.cards[index];
+        let effects = card.instructions().enumerate().map(|(read, instruction)| effect(instruction, read)).collect::<Vec<_>>();
+        let alternatives = card.alternatives.iter()
+            .map(|alternative| (alternative.read, effect(&alternative.instruction, alternative.read)))
+            .collect::<Vec<_>>();
+        (effects, alternatives)
+    });
+    loop {
+        let refined = group_by(&order, |index| {
+            let card = &program.cards[index];
+            let group = |instruction: &Instruction| instruction.next_card.map(|next| groups[&next]);
+            let alternatives = card.alternatives.iter().map(|alternative| group(&alternative.instruction)).collect::<Vec<_>>();
+            (groups[&index], card.instructions().map(group).collect::<Vec<_>>(), alternatives)
+        });
+        let group_count = |groups: &HashMap<usize, usize>| groups.values().max().map_or(0, |max| max + 1);
+        if group_count(&refined) == group_count(&groups) {
+            break;
+        }
+        groups = refined;
+    }
+
+    // The first card of each group in `order` represents the group
+    let mut representatives: HashMap<usize, usize> = HashMap::new();
+    let mut merged = Vec::new();
+    for &index in &order {
+        let representative = *representatives.entry(groups[&index]).or_insert(index);
+        if representative != index {
+            merged.push((program.cards[index].name.clone(), program.cards[representative].name.clone()));
+        }
+    }
+    let representative = |index: usize| representatives[&groups[&index]];
+
+    let kept = reachable_in_order(program, |index| {
+        program.cards[index].next_cards().map(representative).collect()
+    });
+    let new_index: HashMap<usize, usize> = kept.iter().enumerate().map(|(new, &old)| (old, new)).collect();
+    let renumber = |instruction: &Instruction| Instruction {
+        next_card: instruction.next_card.map(|next| new_index[&representative(next)]),
+        ..instruction.clone()
+    };
+    let cards = kept.iter()
+        .map(|&index| {
+            let card = &program.cards[index];
+            Card {
+                name: card.name.clone(),
+                tape_on: renumber(&card.tape_on),
+                tape_off: renumber(&card.tape_off),
+                other_instructions: card.other_instructions.iter().map(renumber).collect(),
+                alternatives: card.alternatives.iter()
+                    .map(|alternative| Alternative {
+                        read: alternative.read,
+                        instruction: renumber(&alternative.instruction),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Minimisation {
+        program: Program {
+            name: program.name.clone(),
+            description: program.description.clone(),
+            initial_card: 0,
+            cards,
+        },
+        unreachable,
+        merged,
+    }
+}
+
+/// Cards reachable from the initial card, in breadth-first order.
+fn reachable_in_order(program: &Program, next_cards: impl Fn(usize) -> Vec<usize>) -> Vec<usize> {
+    let mut order = vec![program.initial_card];
+    let mut queue = VecDeque::from([program.initial_card]);
+    while let Some(index) = queue.pop_front() {
+        for next in next_cards(index) {
+            if !order.contains(&next) {
+                order.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    order
+}
+
+/// Numbers the distinct keys in order of appearance, and maps each card to the number of its key.
+fn group_by<K: Eq + std::hash::Hash>(cards: &[usize], key: impl Fn(usize) -> K) -> HashMap<usize, usize> {
+    let mut numbers: HashMap<K, usize> = HashMap::new();
+    cards.iter()
+        .map(|&index| {
+            let count = numbers.len();
+            (index, *numbers.entry(key(index)).or_insert(count))
+        })
+        .collect()
+}
+
+/// What an instruction does to the tapes and heads, ignoring the next card, when the heads
+/// read `read` as in `Card::instruction`.
+fn effect(instruction: &Instruction, read: usize) -> (Vec<(bool, i8)>, bool) {
+    let action = |tape: usize, write_symbol: Option<bool>, move_direction: Option<Direction>| {
+        let direction = match move_direction {
+            None => 0,
+            Some(Direction::Left) => -1,
+            Some(Direction::Right) => 1,
+            Some(Direction::Up) => 2,
+            Some(Direction::Down) => -2,
+        };
+        (write_symbol.unwrap_or(read >> tape & 1 == 1), direction)
+    };
+    let actions = iter::once(action(0, instruction.write_symbol, instruction.move_direction))
+        .chain(instruction.other_tapes.iter().enumerate().map(|(index, other)| action(index + 1, other.write_symbol, other.move_direction)))
+        .collect();
+    (actions, instruction.next_card.is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::equivalence::structural_diff;
+    use crate::minimise::minimise;
+    use crate::program::{Card, Direction, Instruction, Program};
+    use crate::programs;
+
+    fn instruction(move_direction: Direction, next_card: Option<usize>) -> Instruction {
+        Instruction {
+            write_symbol: None,
+            move_direction: Some(move_direction),
+            next_card,
+            other_tapes: Vec::new(),
+        }
+    }
+
+    fn card(name: &str, tape_on: Instruction, tape_off: Instruction) -> Card {
+        Card {
+            name: name.to_string(),
+            tape_on,
+            tape_off,
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn solutions_are_minimal() {
+        for program in programs::builtins().iter().filter(|program| program.name.ends_with("_solution")) {
+            let minimisation = minimise(program);
+            assert!(minimisation.unreachable.is_empty(), "{}", program.name);
+            assert!(minimisation.merged.is_empty(), "{}", program.name);
+            assert!(structural_diff(program, &minimisation.program).is_isomorphic(), "{}", program.name);
+        }
+    }
+
+    #[test]
+    fn removes_and_merges_cards() {
+        // Walk right until a lit cell, alternating between two identical cards
+        let program = Program {
+            name: "walk".to_string(),
+            description: String::new(),
+            initial_card: 1,
+            cards: vec![
+                card("Unused", instruction(Direction::Left, None), instruction(Direction::Left, Some(0))),
+                card("Even", instruction(Direction::Right, None), instruction(Direction::Right, Some(2))),
+                card("Odd", instruction(Direction::Right, None), instruction(Direction::Right, Some(1))),
+            ],
+        };
+        let minimisation = minimise(&program);
+        assert_eq!(minimisation.unreachable, vec!["Unused"]);
+        assert_eq!(minimisation.merged, vec![("Odd".to_string(), "Even".to_string())]);
+        let minimised = minimisation.program;
+        assert_eq!(minimised.cards.len(), 1);
+        assert_eq!(minimised.cards[0].name, "Even");
+        assert_eq!(minimised.cards[0].tape_off.next_card, Some(0));
+    }
+
+    #[test]
+    fn orders_cards_from_initial_card() {
+        let mut program = programs::builtins().into_iter().find(|program| program.name == "addition_solution").unwrap();
+        program.cards.reverse();
+        for card in program.cards.iter_mut() {
+            for instruction in [&mut card.tape_on, &mut card.tape_off] {
+                instruction.next_card = instruction.next_card.map(|index| 2 - index);
+            }
+        }
+        program.initial_card = 2;
+        let names: Vec<String> = minimise(&program).program.cards.into_iter().map(|card| card.name).collect();
+        assert_eq!(names, vec!["First", "Second", "Remove"]);
+    }
+}
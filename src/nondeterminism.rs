@@ -0,0 +1,172 @@
+use crate::execution::{LevelExecution, TestCaseExecution, TestCaseExecutionState};
+use crate::level::Level;
+use crate::program::Program;
+
+/// Most branches to keep at once when none is given
+pub const DEFAULT_MAX_BRANCHES: usize = 10_000;
+
+/// A step of a branch: the card, what the heads read and which of the card's choices was taken,
+/// as given to `TestCaseExecution::step_choice`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Choice {
+    pub card: usize,
+    pub read: usize,
+    pub choice: usize,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum SearchOutcome {
+    /// A branch halted without errors
+    Accepted,
+    /// Every branch halted, all of them with errors
+    Rejected,
+    /// Some branches were still running after the most steps, or there were too many of them
+    Exhausted,
+}
+
+/// How the search of a test case went. Its execution is the accepted branch, or otherwise the
+/// first branch that halted, or otherwise a branch that was still running.
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub outcome: SearchOutcome,
+    /// Choices of the branch from the start, the witness of an accepted test case
+    pub path: Vec<Choice>,
+    /// Amount of branches that were stepped over the whole search
+    pub branches: u64,
+}
+
+#[derive(Clone)]
+struct Branch {
+    execution: TestCaseExecution,
+    path: Vec<Choice>,
+}
+
+/// Explores every choice of a nondeterministic program breadth-first, so the shortest accepting
+/// branch of each test case is found first. A test case is given up on after `max_steps` steps
+/// or once more than `max_branches` branches are running at the same time.
+///
+/// Deterministic programs have a single branch, so this is the same as `LevelExecution::run`.
+pub fn explore(level: Level, program: Program, max_steps: u64, max_branches: usize) -> (LevelExecution, Vec<Search>) {
+    let mut execution = LevelExecution::new(level, program);
+    let (executions, searches) = execution.executions.drain(..)
+        .map(|tce| explore_case(tce, max_steps, max_branches))
+        .unzip();
+    execution.executions = executions;
+    (execution, searches)
+}
+
+fn explore_case(execution: TestCaseExecution, max_steps: u64, max_branches: usize) -> (TestCaseExecution, Search) {
+    let mut frontier = vec![Branch { execution, path: Vec::new() }];
+    let mut rejected: Option<Branch> = None;
+    let mut branches = 0;
+    let finish = |branch: Branch, outcome, branches| (branch.execution, Search { outcome, path: branch.path, branches });
+
+    for _ in 0..max_steps {
+        if frontier.is_empty() || frontier.len() > max_branches {
+            break;
+        }
+        let mut next = Vec::new();
+        for branch in frontier {
+            let card = branch.execution.get_current_card_index().unwrap();
+            let read = branch.execution.get_read();
+            for choice in 0..branch.execution.get_choice_count() {
+                let mut child = branch.clone();
+                child.execution.step_choice(choice);
+                child.path.push(Choice { card, read, choice });
+                branches += 1;
+                match child.execution.get_state() {
+                    TestCaseExecutionState::Success => return finish(child, SearchOutcome::Accepted, branches),
+                    TestCaseExecutionState::Failure { .. } => {
+                        rejected.get_or_insert(child);
+                    }
+                    _ => next.push(child),
+                }
+            }
+        }
+        frontier = next;
+    }
+
+    match (rejected, frontier.into_iter().next()) {
+        (Some(branch), None) => finish(branch, SearchOutcome::Rejected, branches),
+        (Some(branch), Some(_)) => finish(branch, SearchOutcome::Exhausted, branches),
+        (None, Some(branch)) => finish(branch, SearchOutcome::Exhausted, branches),
+        (None, None) => unreachable!("A branch either halts or keeps running"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution::LevelExecution;
+    use crate::level::{Level, Target, TestCase};
+    use crate::levels;
+    use crate::nondeterminism::{explore, SearchOutcome};
+    use crate::program::Program;
+    use crate::program_dto::ProgramDto;
+    use crate::programs;
+
+    fn find_level(name: &str) -> Level {
+        levels::builtins().into_iter().find(|level| level.name == name).unwrap()
+    }
+
+    fn find_program(name: &str) -> Program {
+        programs::builtins().into_iter().find(|program| program.name == name).unwrap()
+    }
+
+    #[test]
+    fn finds_the_shortest_witness() {
+        let (execution, searches) = explore(find_level("lucky_moth"), find_program("lucky_moth_solution"), 100, 100);
+        assert!(execution.is_solved());
+        assert!(searches.iter().all(|search| search.outcome == SearchOutcome::Accepted));
+        // The light at -7 is found by taking the second choice of the first card
+        let path = &searches[1].path;
+        assert_eq!(path.len(), 8);
+        assert_eq!((path[0].card, path[0].read, path[0].choice), (0, 0, 1));
+        assert!(path[1..].iter().all(|choice| choice.card == 2 && choice.choice == 0));
+        assert_eq!(execution.executions[1].get_current_position(), -7);
+    }
+
+    #[test]
+    fn deterministic_programs_run_as_usual() {
+        let (explored, searches) = explore(find_level("moth"), find_program("moth_solution"), 10_000, 1);
+        let mut run = LevelExecution::new(find_level("moth"), find_program("moth_solution"));
+        run.run(10_000);
+        assert!(explored.is_solved());
+        assert_eq!(explored.get_steps(), run.get_steps());
+        assert!(searches.iter().all(|search| search.branches == search.path.len() as u64));
+    }
+
+    #[test]
+    fn gives_up_on_too_many_branches() {
+        let (execution, searches) = explore(find_level("lucky_moth"), find_program("lucky_moth_solution"), 100, 1);
+        assert_eq!(searches[1].outcome, SearchOutcome::Exhausted);
+        assert!(!execution.is_solved());
+    }
+
+    #[test]
+    fn rejects_when_every_branch_fails() {
+        let yaml = r#"
+name: wrong_guess
+initial_card: Guess
+cards:
+  - name: Guess
+    instruction_on: { write_symbol: null, move_direction: null, next_card: null }
+    instruction_off: { write_symbol: null, move_direction: Left, next_card: null }
+    alternatives:
+      - read: [false]
+        tapes: [{ write_symbol: true, move_direction: null }]
+        next_card: null
+"#;
+        let program = Program::try_from(serde_yaml::from_str::<ProgramDto>(yaml).unwrap()).unwrap();
+        let level = Level {
+            name: "right".to_string(),
+            description: String::new(),
+            cases: vec![TestCase { target: Some(Target::Position { position: 1 }), ..TestCase::default() }],
+            solution: None,
+            hints: Vec::new(),
+        };
+        let (execution, searches) = explore(level, program, 100, 100);
+        assert_eq!(searches[0].outcome, SearchOutcome::Rejected);
+        assert_eq!(searches[0].branches, 2);
+        assert_eq!(execution.executions[0].get_current_position(), -1);
+    }
+}
@@ -0,0 +1,21 @@
+use crate::level::Level;
+use crate::progress::Progress;
+
+#[derive(Clone)]
+pub struct Pack {
+    pub name: String,
+    pub description: String,
+    pub entries: Vec<PackEntry>,
+}
+
+#[derive(Clone)]
+pub struct PackEntry {
+    pub level: Level,
+    pub requires: Vec<String>,
+}
+
+impl PackEntry {
+    pub fn is_unlocked(&self, progress: &Progress) -> bool {
+        self.requires.iter().all(|name| progress.is_solved(name))
+    }
+}
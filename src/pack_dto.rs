@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+use serde::de::Error;
+
+use crate::level::Level;
+use crate::pack::{Pack, PackEntry};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct PackDto {
+    pub name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    levels: Vec<PackEntryDto>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+struct PackEntryDto {
+    level: String,
+    #[serde(default)]
+    requires: Vec<String>,
+}
+
+impl PackDto {
+    /// Resolves the level names of the manifest against `levels`.
+    /// Prerequisites must be listed earlier in the same pack.
+    pub fn try_into_pack(self, levels: &[Level]) -> Result<Pack, serde_yaml::Error> {
+        if self.name.is_empty() {
+            return Err(Error::custom("Name cannot be empty"));
+        }
+        // The name is the directory the pack is installed to, so it must not lead anywhere else
+        let mut components = Path::new(&self.name).components();
+        let is_directory_name = match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => name == self.name.as_str(),
+            _ => false,
+        };
+        if !is_directory_name {
+            return Err(Error::custom(format!("Name must be a plain directory name: {}", self.name)));
+        }
+
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        for entry in self.levels {
+            for required in &entry.requires {
+                if !seen.contains(required) {
+                    return Err(Error::custom(format!("Level {} requires {}, which is not listed before it", entry.level, required)));
+                }
+            }
+            let level = levels.iter()
+                .find(|level| level.name == entry.level)
+                .ok_or::<serde_yaml::Error>(Error::custom(format!("Level does not exist: {}", entry.level)))?;
+            if !seen.insert(entry.level.clone()) {
+                return Err(Error::custom(format!("Duplicate level: {}", entry.level)));
+            }
+            entries.push(PackEntry {
+                level: level.clone(),
+                requires: entry.requires,
+            });
+        }
+
+        Ok(Pack {
+            name: self.name,
+            description: self.description,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels;
+    use crate::pack_dto::PackDto;
+
+    fn parse(yaml: &str) -> PackDto {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn tutorial() {
+        let dto = parse(include_str!("../res/pack/tutorial.yaml"));
+        let pack = dto.try_into_pack(&levels::builtins()).unwrap();
+        assert_eq!(pack.name, "tutorial");
+        assert_eq!(pack.entries[0].level.name, "sandbox");
+        assert_eq!(pack.entries[1].requires, vec!["sandbox".to_string()]);
+    }
+
+    #[test]
+    fn unknown_level() {
+        let dto = parse("name: broken\nlevels:\n  - level: nonexistent\n");
+        assert!(dto.try_into_pack(&levels::builtins()).is_err());
+    }
+
+    #[test]
+    fn names_that_are_not_directory_names() {
+        for name in ["..", ".", "../../..", "/tmp", "a/b", "a/", "'a/..'"] {
+            let dto = parse(&format!("name: {}\n", name));
+            assert!(dto.try_into_pack(&levels::builtins()).is_err(), "{} was accepted", name);
+        }
+    }
+
+    #[test]
+    fn requirement_listed_later() {
+        let dto = parse("name: broken\nlevels:\n  - level: move8\n    requires: [ sandbox ]\n  - level: sandbox\n");
+        assert!(dto.try_into_pack(&levels::builtins()).is_err());
+    }
+}
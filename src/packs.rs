@@ -0,0 +1,16 @@
+use crate::levels;
+use crate::pack::Pack;
+use crate::pack_dto::PackDto;
+
+fn tutorial() -> Pack {
+    serde_yaml::from_str::<PackDto>(include_str!("../res/pack/tutorial.yaml"))
+        .unwrap()
+        .try_into_pack(&levels::builtins())
+        .unwrap()
+}
+
+pub fn builtins() -> Vec<Pack> {
+    vec![
+        tutorial(),
+    ]
+}
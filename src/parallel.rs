@@ -0,0 +1,82 @@
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::execution::LevelExecution;
+use crate::level::Level;
+use crate::nondeterminism;
+use crate::program::Program;
+
+/// Amount of threads to use when none is given: one per processor.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Applies `f` to every item on up to `jobs` threads. The results are in the order of the
+/// items, no matter which thread finished first.
+pub fn map<T: Send, R: Send>(items: Vec<T>, jobs: usize, f: impl Fn(T) -> R + Sync) -> Vec<R> {
+    let jobs = jobs.min(items.len());
+    if jobs <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                let Some((index, item)) = next else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Runs every program on its level, giving each test case at most `max_steps` steps.
+/// Nondeterministic programs are explored, keeping the branch of each test case that was accepted.
+/// The executions are in the same order as the pairs.
+pub fn run_levels(pairs: Vec<(Level, Program)>, max_steps: u64, jobs: usize) -> Vec<LevelExecution> {
+    map(pairs, jobs, |(level, program)| {
+        if program.is_nondeterministic() {
+            return nondeterminism::explore(level, program, max_steps, nondeterminism::DEFAULT_MAX_BRANCHES).0;
+        }
+        let mut execution = LevelExecution::new(level, program);
+        execution.run(max_steps);
+        execution
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels;
+    use crate::parallel::{map, run_levels};
+    use crate::programs;
+
+    #[test]
+    fn results_are_in_order() {
+        let items: Vec<u64> = (0..100).collect();
+        let squares = map(items.clone(), 8, |n| n * n);
+        assert_eq!(squares, items.iter().map(|n| n * n).collect::<Vec<_>>());
+        assert!(map(Vec::<u64>::new(), 8, |n| n).is_empty());
+    }
+
+    #[test]
+    fn runs_levels_with_their_solutions() {
+        let pairs: Vec<_> = levels::builtins().into_iter()
+            .filter_map(|level| {
+                let program = programs::builtins().into_iter().find(|program| Some(&program.name) == level.solution.as_ref())?;
+                Some((level, program))
+            })
+            .collect();
+        let names: Vec<String> = pairs.iter().map(|(level, _)| level.name.clone()).collect();
+        let executions = run_levels(pairs, 10_000, 4);
+        assert_eq!(executions.iter().map(|execution| execution.level.name.clone()).collect::<Vec<_>>(), names);
+        assert!(executions.iter().all(|execution| execution.is_solved()));
+    }
+}
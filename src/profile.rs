@@ -0,0 +1,102 @@
+use std::ops::ControlFlow;
+
+use crate::execution::{Cell, ExecutionObserver, LevelExecution, Step};
+use crate::level::Level;
+use crate::program::Program;
+
+/// Statistics about a run of a program, collected by observing its execution.
+#[derive(Clone, Default, Debug, Eq, PartialEq)]
+pub struct Profile {
+    /// How many times each instruction of each card fired, in the order of `Card::all_instructions`
+    pub instructions: Vec<Vec<u64>>,
+    /// Amount of symbols written to any tape, even if the cell already held it
+    pub writes: u64,
+    /// Total distance the head moved, in moves between neighbouring cells
+    pub travel: u64,
+    pub leftmost: i64,
+    pub rightmost: i64,
+    pub steps: u64,
+}
+
+impl Profile {
+    pub fn new(program: &Program) -> Profile {
+        Profile {
+            instructions: program.cards.iter().map(|card| vec![0; card.all_instructions().count()]).collect(),
+            ..Profile::default()
+        }
+    }
+
+    /// How many times any instruction of the card fired
+    pub fn card_count(&self, card: usize) -> u64 {
+        self.instructions[card].iter().sum()
+    }
+
+    /// Adds the statistics of another run of the same program.
+    pub fn merge(&mut self, other: &Profile) {
+        for (counts, other_counts) in self.instructions.iter_mut().zip(&other.instructions) {
+            counts.iter_mut().zip(other_counts).for_each(|(count, other_count)| *count += other_count);
+        }
+        self.writes += other.writes;
+        self.travel += other.travel;
+        self.leftmost = self.leftmost.min(other.leftmost);
+        self.rightmost = self.rightmost.max(other.rightmost);
+        self.steps += other.steps;
+    }
+}
+
+impl ExecutionObserver for Profile {
+    fn on_step(&mut self, step: &Step) -> ControlFlow<()> {
+        self.instructions[step.card][step.instruction] += 1;
+        self.travel += step.from.distance(&step.to);
+        self.leftmost = self.leftmost.min(step.to.x());
+        self.rightmost = self.rightmost.max(step.to.x());
+        self.steps += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn on_write(&mut self, _tape: usize, _cell: Cell, _on: bool) {
+        self.writes += 1;
+    }
+}
+
+/// Runs the program on every test case of the level, giving each at most `max_steps` steps,
+/// and returns the execution along with a profile for each test case.
+pub fn profile(level: Level, program: Program, max_steps: u64) -> (LevelExecution, Vec<Profile>) {
+    let empty = Profile::new(&program);
+    let mut execution = LevelExecution::new(level, program);
+    let profiles = execution.executions.iter_mut()
+        .map(|tce| {
+            let mut profile = empty.clone();
+            tce.run_observed(max_steps, &mut profile);
+            profile
+        })
+        .collect();
+    (execution, profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels;
+    use crate::profile::{profile, Profile};
+    use crate::programs;
+
+    #[test]
+    fn profiles_moth() {
+        let level = levels::builtins().into_iter().find(|level| level.name == "moth").unwrap();
+        let program = programs::builtins().into_iter().find(|program| program.name == "moth_solution").unwrap();
+        let (execution, profiles) = profile(level, program.clone(), 10_000);
+        assert!(execution.is_solved());
+        assert_eq!(profiles.len(), execution.executions.len());
+
+        let mut total = Profile::new(&program);
+        for (tce, profile) in execution.executions.iter().zip(&profiles) {
+            assert_eq!(profile.steps, tce.steps);
+            let fired: u64 = (0..program.cards.len()).map(|card| profile.card_count(card)).sum();
+            assert_eq!(fired, tce.steps);
+            assert!(profile.leftmost <= tce.get_current_position() && tce.get_current_position() <= profile.rightmost);
+            assert!(profile.travel >= profile.rightmost.abs_diff(profile.leftmost));
+            total.merge(profile);
+        }
+        assert_eq!(total.steps, execution.get_steps());
+    }
+}
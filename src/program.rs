@@ -6,14 +6,86 @@ pub struct Program {
     pub cards: Vec<Card>,
 }
 
+impl Program {
+    /// Amount of tapes the program reads and writes, each with its own head
+    pub fn tapes(&self) -> usize {
+        self.cards.first().map_or(1, |card| 1 + card.tape_off.other_tapes.len())
+    }
+
+    /// Whether any card has more than one instruction for the same symbols
+    pub fn is_nondeterministic(&self) -> bool {
+        self.cards.iter().any(|card| !card.alternatives.is_empty())
+    }
+}
+
 #[derive(Clone)]
 pub struct Card {
     pub name: String,
     pub tape_on: Instruction,
     pub tape_off: Instruction,
+    /// For programs with more than one tape, the instructions for when a head other than the
+    /// first one is on a lit cell. See `instruction` for the order.
+    pub other_instructions: Vec<Instruction>,
+    /// For nondeterministic programs, instructions to choose from besides the one given by
+    /// `instruction`. Executions that don't explore the choices always take the first one.
+    pub alternatives: Vec<Alternative>,
+}
+
+/// Another instruction a card can choose when the heads read `read`, as given to `Card::instruction`.
+#[derive(Clone)]
+pub struct Alternative {
+    pub read: usize,
+    pub instruction: Instruction,
 }
 
 impl Card {
+    /// The instruction for what the heads read, with bit `i` of `read` set if head `i` is on a
+    /// lit cell. Only the first head matters for programs with one tape.
+    pub fn instruction(&self, read: usize) -> &Instruction {
+        match read {
+            0 => &self.tape_off,
+            1 => &self.tape_on,
+            _ => &self.other_instructions[read - 2],
+        }
+    }
+
+    /// Every instruction of the card, in the order of `instruction`
+    pub fn instructions(&self) -> impl Iterator<Item=&Instruction> {
+        [&self.tape_off, &self.tape_on].into_iter().chain(&self.other_instructions)
+    }
+
+    /// Every instruction of the card with what the heads read for it and its place among `choices`
+    /// for that: those of `instructions` first, then the alternatives
+    pub fn all_instructions(&self) -> impl Iterator<Item=(usize, usize, &Instruction)> {
+        let alternatives = self.alternatives.iter().enumerate().map(move |(index, alternative)| {
+            let choice = 1 + self.alternatives[..index].iter().filter(|other| other.read == alternative.read).count();
+            (alternative.read, choice, &alternative.instruction)
+        });
+        self.instructions().enumerate().map(|(read, instruction)| (read, 0, instruction)).chain(alternatives)
+    }
+
+    /// Index in `all_instructions` of the instruction at `choice` of `choices(read)`
+    pub fn instruction_index(&self, read: usize, choice: usize) -> usize {
+        match choice {
+            0 => read,
+            _ => self.all_instructions().position(|(other_read, other_choice, _)| (other_read, other_choice) == (read, choice)).unwrap(),
+        }
+    }
+
+    /// The instructions to choose from when the heads read `read`, starting with `instruction`
+    pub fn choices(&self, read: usize) -> impl Iterator<Item=&Instruction> {
+        std::iter::once(self.instruction(read)).chain(self.alternatives.iter()
+            .filter(move |alternative| alternative.read == read)
+            .map(|alternative| &alternative.instruction))
+    }
+
+    /// Indices of the cards that this card can continue with
+    pub fn next_cards(&self) -> impl Iterator<Item=usize> + '_ {
+        self.instructions()
+            .chain(self.alternatives.iter().map(|alternative| &alternative.instruction))
+            .filter_map(|instruction| instruction.next_card)
+    }
+
     pub fn terminate() -> Card {
         Card {
             name: "Terminate".to_string(),
@@ -21,12 +93,16 @@ impl Card {
                 write_symbol: None,
                 move_direction: None,
                 next_card: None,
+                other_tapes: Vec::new(),
             },
             tape_off: Instruction {
                 write_symbol: None,
                 move_direction: None,
                 next_card: None,
+                other_tapes: Vec::new(),
             },
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 
@@ -37,12 +113,16 @@ impl Card {
                 write_symbol: None,
                 move_direction: Some(Direction::Left),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
             tape_off: Instruction {
                 write_symbol: Some(true),
                 move_direction: Some(Direction::Right),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 
@@ -53,12 +133,16 @@ impl Card {
                 write_symbol: None,
                 move_direction: Some(Direction::Left),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
             tape_off: Instruction {
                 write_symbol: Some(true),
                 move_direction: Some(Direction::Left),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 
@@ -69,12 +153,16 @@ impl Card {
                 write_symbol: None,
                 move_direction: Some(Direction::Right),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
             tape_off: Instruction {
                 write_symbol: None,
                 move_direction: Some(Direction::Right),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 
@@ -85,12 +173,16 @@ impl Card {
                 write_symbol: None,
                 move_direction: Some(Direction::Left),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
             tape_off: Instruction {
                 write_symbol: None,
                 move_direction: Some(Direction::Left),
                 next_card: Some(0),
+                other_tapes: Vec::new(),
             },
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
         }
     }
 }
@@ -100,10 +192,23 @@ pub struct Instruction {
     pub write_symbol: Option<bool>,
     pub move_direction: Option<Direction>,
     pub next_card: Option<usize>,
+    /// For programs with more than one tape, what to do with the tapes other than the first one
+    pub other_tapes: Vec<TapeAction>,
+}
+
+/// What an instruction writes to and how it moves the head of one tape.
+#[derive(Clone, Default)]
+pub struct TapeAction {
+    pub write_symbol: Option<bool>,
+    pub move_direction: Option<Direction>,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Direction {
     Left,
     Right,
+    /// Only moves the head on a grid, and leaves it where it is on a line
+    Up,
+    /// Only moves the head on a grid, and leaves it where it is on a line
+    Down,
 }
@@ -1,106 +1,306 @@
 use std::collections::{HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
-use serde::de::Error;
 
-use crate::program::{Card, Direction, Instruction, Program};
-use crate::program::Direction::{Left, Right};
+use crate::file_error::{FileError, FileErrors, Subject};
+use crate::program::{Alternative, Card, Direction, Instruction, Program, TapeAction};
+use crate::program::Direction::{Down, Left, Right, Up};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub struct ProgramDto {
-    name: String,
+    pub(crate) name: String,
     #[serde(default)]
-    description: String,
-    initial_card: String,
+    pub(crate) description: String,
+    pub(crate) initial_card: String,
+    #[serde(default = "one_tape", skip_serializing_if = "is_one_tape")]
+    pub(crate) tapes: usize,
     #[serde(default)]
-    cards: Vec<CardDto>,
+    pub(crate) cards: Vec<CardDto>,
+}
+
+fn one_tape() -> usize {
+    1
+}
+
+fn is_one_tape(tapes: &usize) -> bool {
+    *tapes == 1
 }
 
 impl From<Program> for ProgramDto {
     fn from(program: Program) -> Self {
         let card_names: Vec<String> = program.cards.iter().map(|card| card.name.clone()).collect();
         let initial_card = card_names[program.initial_card].clone();
-        let cards: Vec<CardDto> = program.cards.into_iter().map(CardDto::from_card(&card_names)).collect();
+        let tapes = program.tapes();
+        let cards: Vec<CardDto> = program.cards.into_iter().map(CardDto::from_card(&card_names, tapes)).collect();
         ProgramDto {
             name: program.name,
             description: program.description,
             initial_card,
+            tapes,
             cards,
         }
     }
 }
 
 impl TryFrom<ProgramDto> for Program {
-    type Error = serde_yaml::Error;
+    type Error = FileErrors;
 
+    /// Checks every card, and fails with all the mistakes found
     fn try_from(dto: ProgramDto) -> Result<Self, Self::Error> {
-        let name = if dto.name.is_empty() {
-            return Err(Error::custom("Name cannot be empty"));
-        } else {
-            dto.name
-        };
-
-        let description = dto.description;
+        let mut errors = Vec::new();
+        if dto.name.is_empty() {
+            errors.push(FileError::new(Subject::Field("name"), "Name cannot be empty"));
+        }
 
-        let duplicate_card_names = retain_duplicates(dto.cards.iter().map(|card| card.name.clone()).collect());
-        if !duplicate_card_names.is_empty() {
-            let message = duplicate_card_names.into_iter().collect::<Vec<String>>().join(", ");
-            return Err(Error::custom(format!("Duplicate card names: {}", message)));
+        let mut card_names = HashSet::new();
+        for card in &dto.cards {
+            if !card_names.insert(card.name.as_str()) {
+                errors.push(FileError::new(Subject::Card(card.name.clone()), format!("Duplicate card name: {}", card.name)));
+            }
         }
 
         let card_name_map: HashMap<String, usize> = dto.cards.iter()
             .enumerate().map(|(index, card)| (card.name.clone(), index))
             .collect();
 
-        let &initial_card = card_name_map.get(&dto.initial_card).ok_or::<Self::Error>(Error::custom(format!("Initial card not found: {}", dto.initial_card)))?;
+        let initial_card = card_name_map.get(&dto.initial_card).copied();
+        if initial_card.is_none() {
+            errors.push(FileError::new(Subject::Field("initial_card"), format!("Initial card not found: {}", dto.initial_card))
+                .suggest(&dto.initial_card, card_names.iter().copied()));
+        }
 
-        let cards = dto.cards.iter().map(|card| card.try_into(&card_name_map)).collect::<Result<_, _>>()?;
+        if dto.tapes == 0 || dto.tapes > MAX_TAPES {
+            errors.push(FileError::new(Subject::Field("tapes"), format!("A program must have between 1 and {} tapes", MAX_TAPES)));
+            return Err(errors.into());
+        }
 
-        Ok(Program {
-            name,
-            description,
-            initial_card,
-            cards,
-        })
+        let mut cards = Vec::new();
+        for card in &dto.cards {
+            match card.try_into(&card_name_map, dto.tapes) {
+                Ok(card) => cards.push(card),
+                Err(card_errors) => errors.extend(card_errors),
+            }
+        }
+
+        match initial_card {
+            Some(initial_card) if errors.is_empty() => Ok(Program {
+                name: dto.name,
+                description: dto.description,
+                initial_card,
+                cards,
+            }),
+            _ => Err(errors.into()),
+        }
     }
 }
 
+/// Programs with more tapes have instructions for exponentially many combinations of symbols
+pub(crate) const MAX_TAPES: usize = 8;
+
+/// A card of a program with one tape has an instruction for when the tape is on and for when it
+/// is off. A card of a program with more tapes has a list of instructions instead, one for
+/// every combination of symbols that the heads can read.
+///
+/// Cards of nondeterministic programs also list alternatives, which are more instructions for
+/// symbols that already have one. They are written like the instructions of a program with more
+/// tapes, whatever the amount of tapes.
+///
+/// A subroutine card has no instructions. It runs another program instead, and continues with
+/// `next_card` once that program halts. See `ProgramDto::resolve`.
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub struct CardDto {
-    name: String,
-    instruction_on: InstructionDto,
-    instruction_off: InstructionDto,
+    pub(crate) name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) instruction_on: Option<InstructionDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) instruction_off: Option<InstructionDto>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) instructions: Vec<MultiTapeInstructionDto>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) alternatives: Vec<MultiTapeInstructionDto>,
+    /// Name of the program that a subroutine card runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) subroutine: Option<String>,
+    /// The card that a subroutine card continues with, or none to halt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) next_card: Option<String>,
 }
 
 impl CardDto {
-    fn try_into(&self, card_name_map: &HashMap<String, usize>) -> Result<Card, serde_yaml::Error> {
-        let card = Card {
+    fn try_into(&self, card_name_map: &HashMap<String, usize>, tapes: usize) -> Result<Card, Vec<FileError>> {
+        let error = |message: String| FileError::new(Subject::Card(self.name.clone()), message);
+        if let Some(subroutine) = &self.subroutine {
+            let next = InstructionDto { write_symbol: None, move_direction: None, next_card: self.next_card.clone() };
+            let mut errors = vec![error(format!("Card {} runs program {}, which was not resolved", self.name, subroutine))];
+            errors.extend(next.try_into_with_map(&self.name, card_name_map).err());
+            return Err(errors);
+        }
+        let mut errors = Vec::new();
+        let mut alternatives = Vec::new();
+        for dto in &self.alternatives {
+            if dto.read.len() != tapes || dto.tapes.len() != tapes {
+                errors.push(error(format!("Every alternative of card {} must read and act on {} tapes", self.name, tapes)));
+                continue;
+            }
+            match dto.try_into_with_map(&self.name, card_name_map) {
+                Ok(instruction) => alternatives.push(Alternative { read: read_bits(&dto.read), instruction }),
+                Err(err) => errors.push(err),
+            }
+        }
+        if tapes == 1 {
+            let single = |instruction: &Option<InstructionDto>, symbol: &str| match instruction {
+                Some(instruction) if self.instructions.is_empty() => instruction.try_into_with_map(&self.name, card_name_map),
+                Some(_) => Err(error(format!("Card {} of a program with one tape cannot have a list of instructions", self.name))),
+                None => Err(error(format!("Card {} has no instruction for when the tape is {}", self.name, symbol))),
+            };
+            let tape_on = single(&self.instruction_on, "on");
+            let tape_off = single(&self.instruction_off, "off");
+            return match (tape_on, tape_off) {
+                (Ok(tape_on), Ok(tape_off)) if errors.is_empty() => Ok(Card {
+                    name: self.name.clone(),
+                    tape_on,
+                    tape_off,
+                    other_instructions: Vec::new(),
+                    alternatives,
+                }),
+                (tape_on, tape_off) => {
+                    errors.extend(tape_on.err());
+                    errors.extend(tape_off.err());
+                    errors.dedup();
+                    Err(errors)
+                }
+            };
+        }
+
+        if self.instruction_on.is_some() || self.instruction_off.is_some() {
+            errors.push(error(format!("Card {} of a program with {} tapes needs a list of instructions instead of instruction_on and instruction_off", self.name, tapes)));
+        }
+        let mut instructions: Vec<Option<Instruction>> = vec![None; 1 << tapes];
+        let mut given = HashSet::new();
+        for dto in &self.instructions {
+            if dto.read.len() != tapes || dto.tapes.len() != tapes {
+                errors.push(error(format!("Every instruction of card {} must read and act on {} tapes", self.name, tapes)));
+                continue;
+            }
+            let read = read_bits(&dto.read);
+            if !given.insert(read) {
+                errors.push(error(format!("Card {} has more than one instruction for reading {}", self.name, describe_read(&dto.read))));
+                continue;
+            }
+            match dto.try_into_with_map(&self.name, card_name_map) {
+                Ok(instruction) => instructions[read] = Some(instruction),
+                Err(err) => errors.push(err),
+            }
+        }
+        for read in (0..1 << tapes).filter(|read| !given.contains(read)) {
+            let symbols: Vec<bool> = (0..tapes).map(|tape| read >> tape & 1 == 1).collect();
+            errors.push(error(format!("Card {} has no instruction for reading {}", self.name, describe_read(&symbols))));
+        }
+        errors.dedup();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let mut instructions = instructions.into_iter().map(Option::unwrap);
+        Ok(Card {
             name: self.name.clone(),
-            tape_on: self.instruction_on.try_into_with_map(card_name_map)?,
-            tape_off: self.instruction_off.try_into_with_map(card_name_map)?,
-        };
-        Ok(card)
+            tape_off: instructions.next().unwrap(),
+            tape_on: instructions.next().unwrap(),
+            other_instructions: instructions.collect(),
+            alternatives,
+        })
     }
 
-    fn from_card<'a>(card_names: &'a[String]) -> impl Fn(Card) -> CardDto + 'a {
-        |card| {
+    fn from_card<'a>(card_names: &'a[String], tapes: usize) -> impl Fn(Card) -> CardDto + 'a {
+        move |card| {
+            let instruction_dto = |instruction: &Instruction| InstructionDto {
+                write_symbol: instruction.write_symbol,
+                move_direction: instruction.move_direction.map(DirectionDto::from),
+                next_card: instruction.next_card.map(|index| card_names[index].clone()),
+            };
+            let multi_tape_instruction_dto = |read: usize, instruction: &Instruction| MultiTapeInstructionDto {
+                read: (0..tapes).map(|tape| read >> tape & 1 == 1).collect(),
+                tapes: std::iter::once((instruction.write_symbol, instruction.move_direction))
+                    .chain(instruction.other_tapes.iter().map(|action| (action.write_symbol, action.move_direction)))
+                    .map(|(write_symbol, move_direction)| TapeActionDto {
+                        write_symbol,
+                        move_direction: move_direction.map(DirectionDto::from),
+                    })
+                    .collect(),
+                next_card: instruction.next_card.map(|index| card_names[index].clone()),
+            };
+            let alternatives = card.alternatives.iter()
+                .map(|alternative| multi_tape_instruction_dto(alternative.read, &alternative.instruction))
+                .collect();
+            if tapes == 1 {
+                return CardDto {
+                    instruction_on: Some(instruction_dto(&card.tape_on)),
+                    instruction_off: Some(instruction_dto(&card.tape_off)),
+                    instructions: Vec::new(),
+                    alternatives,
+                    name: card.name,
+                    next_card: None,
+                    subroutine: None,
+                };
+            }
+            let instructions = card.instructions()
+                .enumerate()
+                .map(|(read, instruction)| multi_tape_instruction_dto(read, instruction))
+                .collect();
             CardDto {
                 name: card.name,
-                instruction_on: InstructionDto {
-                    write_symbol: card.tape_on.write_symbol,
-                    move_direction: card.tape_on.move_direction.map(DirectionDto::from),
-                    next_card: card.tape_on.next_card.map(|index| card_names[index].clone()),
-                },
-                instruction_off: InstructionDto {
-                    write_symbol: card.tape_off.write_symbol,
-                    move_direction: card.tape_off.move_direction.map(DirectionDto::from),
-                    next_card: card.tape_off.next_card.map(|index| card_names[index].clone()),
-                },
+                instruction_on: None,
+                instruction_off: None,
+                instructions,
+                alternatives,
+                next_card: None,
+                subroutine: None,
             }
         }
     }
 }
 
+/// The symbols read by the heads as bits, like `Card::instruction` takes them
+fn read_bits(read: &[bool]) -> usize {
+    read.iter().enumerate().fold(0, |bits, (tape, &on)| bits | (on as usize) << tape)
+}
+
+fn describe_read(read: &[bool]) -> String {
+    read.iter().map(|&on| if on { "on" } else { "off" }).collect::<Vec<_>>().join(", ")
+}
+
+/// An instruction of a program with more than one tape, for when the heads read `read`.
+/// It has what to write and where to move for every tape, in the same order.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct MultiTapeInstructionDto {
+    pub read: Vec<bool>,
+    pub tapes: Vec<TapeActionDto>,
+    pub next_card: Option<String>,
+}
+
+impl MultiTapeInstructionDto {
+    fn try_into_with_map(&self, card: &str, card_name_map: &HashMap<String, usize>) -> Result<Instruction, FileError> {
+        let first = InstructionDto {
+            write_symbol: self.tapes[0].write_symbol,
+            move_direction: self.tapes[0].move_direction,
+            next_card: self.next_card.clone(),
+        };
+        let mut instruction = first.try_into_with_map(card, card_name_map)?;
+        instruction.other_tapes = self.tapes[1..].iter()
+            .map(|action| TapeAction {
+                write_symbol: action.write_symbol,
+                move_direction: action.move_direction.map(Direction::from),
+            })
+            .collect();
+        Ok(instruction)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct TapeActionDto {
+    pub write_symbol: Option<bool>,
+    pub move_direction: Option<DirectionDto>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub struct InstructionDto {
     pub write_symbol: Option<bool>,
@@ -109,9 +309,14 @@ pub struct InstructionDto {
 }
 
 impl InstructionDto {
-    pub fn try_into_with_map(&self, card_name_map: &HashMap<String, usize>) -> Result<Instruction, serde_yaml::Error> {
+    /// Converts an instruction of `card`, looking up the card to continue with by name
+    pub fn try_into_with_map(&self, card: &str, card_name_map: &HashMap<String, usize>) -> Result<Instruction, FileError> {
         let next_card = if let Some(name) = &self.next_card {
-            Some(*card_name_map.get(name).ok_or::<serde_yaml::Error>(Error::custom(format!("Card does not exist: {}", &name)))?)
+            let index = card_name_map.get(name).ok_or_else(|| {
+                FileError::new(Subject::Reference { card: card.to_string(), name: name.clone() }, format!("Card does not exist: {}", name))
+                    .suggest(name, card_name_map.keys().map(String::as_str))
+            })?;
+            Some(*index)
         } else {
             None
         };
@@ -119,6 +324,7 @@ impl InstructionDto {
             write_symbol: self.write_symbol,
             move_direction: self.move_direction.map(|dir| dir.into()),
             next_card,
+            other_tapes: Vec::new(),
         };
         Ok(instruction)
     }
@@ -128,6 +334,8 @@ impl InstructionDto {
 pub enum DirectionDto {
     Left,
     Right,
+    Up,
+    Down,
 }
 
 impl From<DirectionDto> for Direction {
@@ -135,6 +343,8 @@ impl From<DirectionDto> for Direction {
         match direction {
             DirectionDto::Left => Left,
             DirectionDto::Right => Right,
+            DirectionDto::Up => Up,
+            DirectionDto::Down => Down,
         }
     }
 }
@@ -144,14 +354,264 @@ impl From<Direction> for DirectionDto {
         match direction {
             Left => DirectionDto::Left,
             Right => DirectionDto::Right,
+            Up => DirectionDto::Up,
+            Down => DirectionDto::Down,
+        }
+    }
+}
+
+impl ProgramDto {
+    /// Converts to a program like `Program::try_from`, after inlining the programs that
+    /// subroutine cards run. `find` looks up a program by name.
+    ///
+    /// The cards of a subroutine are copied into the program, named after the subroutine card:
+    /// its initial card takes the name of the subroutine card, and the others are prefixed by it,
+    /// as in `Seek/Check`. Where the subroutine would halt, the program continues with the
+    /// `next_card` of the subroutine card instead. Subroutines may run subroutines themselves, but
+    /// not in a cycle.
+    ///
+    /// Mistakes in a subroutine are reported on the subroutine card that runs it, next to the
+    /// mistakes in the rest of the program.
+    pub fn resolve(self, find: &dyn Fn(&str) -> Result<Option<ProgramDto>, FileErrors>) -> Result<Program, FileErrors> {
+        let (dto, mut errors) = inline(self, find, &mut Vec::new());
+        let unresolved: Vec<String> = dto.cards.iter()
+            .filter(|card| card.subroutine.is_some())
+            .map(|card| card.name.clone())
+            .collect();
+        match Program::try_from(dto) {
+            Ok(program) if errors.is_empty() => Ok(program),
+            Ok(_) => Err(errors.into()),
+            Err(conversion_errors) => {
+                // The subroutine cards that were left unresolved already have their mistakes
+                errors.extend(conversion_errors.errors.into_iter()
+                    .filter(|error| !matches!(&error.subject, Subject::Card(card) if unresolved.contains(card))));
+                Err(errors.into())
+            }
         }
     }
 }
 
-fn retain_duplicates(items: Vec<String>) -> HashSet<String> {
-    let mut set = HashSet::new();
-    items.into_iter().filter(|item| !set.insert(item.clone())).collect()
+/// Inlines the subroutines of a program, with `stack` the names of the programs that are being
+/// inlined into each other. Subroutine cards whose program cannot be inlined are left as they
+/// are, next to the mistakes that stopped them.
+fn inline(dto: ProgramDto, find: &dyn Fn(&str) -> Result<Option<ProgramDto>, FileErrors>, stack: &mut Vec<String>) -> (ProgramDto, Vec<FileError>) {
+    if stack.contains(&dto.name) {
+        let cycle = stack.iter().skip_while(|name| **name != dto.name).chain([&dto.name]).cloned().collect::<Vec<_>>();
+        let error = FileError::new(Subject::File, format!("Programs run each other as subroutines: {}", cycle.join(" → ")));
+        return (dto, vec![error]);
+    }
+    if dto.cards.iter().all(|card| card.subroutine.is_none()) {
+        return (dto, Vec::new());
+    }
+
+    stack.push(dto.name.clone());
+    let mut cards = Vec::new();
+    let mut errors = Vec::new();
+    for card in dto.cards {
+        let Some(subroutine) = &card.subroutine else {
+            cards.push(card);
+            continue;
+        };
+        let error = |message: String| FileError::new(Subject::Card(card.name.clone()), message);
+        if card.instruction_on.is_some() || card.instruction_off.is_some() || !card.instructions.is_empty() || !card.alternatives.is_empty() {
+            errors.push(error(format!("Subroutine card {} cannot have instructions", card.name)));
+            cards.push(card);
+            continue;
+        }
+        let callee = match find(subroutine) {
+            Ok(Some(callee)) => callee,
+            Ok(None) => {
+                errors.push(error(format!("Card {} runs program {}, which was not found", card.name, subroutine)));
+                cards.push(card);
+                continue;
+            }
+            Err(callee_errors) => {
+                errors.extend(callee_errors.errors.iter().map(|callee_error| {
+                    let located = FileErrors { path: callee_errors.path.clone(), errors: vec![callee_error.clone()] };
+                    error(format!("Program {} has a mistake: {}", subroutine, located))
+                }));
+                cards.push(card);
+                continue;
+            }
+        };
+        let (callee, callee_errors) = inline(callee, find, stack);
+        if !callee_errors.is_empty() {
+            errors.extend(callee_errors.into_iter().map(|callee_error| FileError { subject: Subject::Card(card.name.clone()), ..callee_error }));
+            cards.push(card);
+            continue;
+        }
+        if callee.tapes != dto.tapes {
+            errors.push(error(format!("Card {} runs program {} with {} tapes, but the program has {}", card.name, subroutine, callee.tapes, dto.tapes)));
+            cards.push(card);
+            continue;
+        }
+        let rename = |name: &String| if *name == callee.initial_card {
+            card.name.clone()
+        } else {
+            format!("{}/{}", card.name, name)
+        };
+        let next_card = |next: &Option<String>| match next {
+            Some(next) => Some(rename(next)),
+            None => card.next_card.clone(),
+        };
+        for callee_card in &callee.cards {
+            let instruction = |dto: &InstructionDto| InstructionDto {
+                next_card: next_card(&dto.next_card),
+                ..*dto
+            };
+            let multi_tape_instruction = |dto: &MultiTapeInstructionDto| MultiTapeInstructionDto {
+                read: dto.read.clone(),
+                tapes: dto.tapes.clone(),
+                next_card: next_card(&dto.next_card),
+            };
+            cards.push(CardDto {
+                name: rename(&callee_card.name),
+                instruction_on: callee_card.instruction_on.as_ref().map(instruction),
+                instruction_off: callee_card.instruction_off.as_ref().map(instruction),
+                instructions: callee_card.instructions.iter().map(multi_tape_instruction).collect(),
+                alternatives: callee_card.alternatives.iter().map(multi_tape_instruction).collect(),
+                subroutine: None,
+                next_card: None,
+            });
+        }
+    }
+    stack.pop();
+    (ProgramDto { cards, ..dto }, errors)
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use std::path::Path;
+
+    use crate::execution::TestCaseExecution;
+    use crate::file_error::FileErrors;
+    use crate::level::TestCase;
+    use crate::program::Program;
+    use crate::program_dto::ProgramDto;
+
+    fn round_trip(yaml: &str) {
+        let dto: ProgramDto = serde_yaml::from_str(yaml).unwrap();
+        let program = Program::try_from(serde_yaml::from_str::<ProgramDto>(yaml).unwrap()).unwrap();
+        assert_eq!(ProgramDto::from(program), dto);
+    }
+
+    #[test]
+    fn round_trip_one_tape() {
+        let yaml = include_str!("../res/program/addition_solution.yaml");
+        round_trip(yaml);
+        assert!(!serde_yaml::to_string(&serde_yaml::from_str::<ProgramDto>(yaml).unwrap()).unwrap().contains("tapes"));
+    }
+
+    #[test]
+    fn round_trip_two_tapes() {
+        let yaml = include_str!("../res/program/copy_solution.yaml");
+        round_trip(yaml);
+        let program = Program::try_from(serde_yaml::from_str::<ProgramDto>(yaml).unwrap()).unwrap();
+        assert_eq!(program.tapes(), 2);
+        let copy = program.cards[0].instruction(0b01);
+        assert_eq!(copy.other_tapes[0].write_symbol, Some(true));
+        assert!(program.cards[0].instruction(0b10).next_card.is_none());
+    }
+
+    #[test]
+    fn round_trip_alternatives() {
+        let yaml = include_str!("../res/program/lucky_moth_solution.yaml");
+        round_trip(yaml);
+        let program = Program::try_from(serde_yaml::from_str::<ProgramDto>(yaml).unwrap()).unwrap();
+        assert!(program.is_nondeterministic());
+        let choices: Vec<_> = program.cards[0].choices(0).map(|instruction| instruction.next_card).collect();
+        assert_eq!(choices, [Some(1), Some(2)]);
+        assert_eq!(program.cards[0].choices(1).count(), 1);
+    }
+
+    #[test]
+    fn every_read_needs_an_instruction() {
+        let yaml = include_str!("../res/program/copy_solution.yaml");
+        let mut dto: ProgramDto = serde_yaml::from_str(yaml).unwrap();
+        dto.cards[0].instructions.remove(2);
+        let error = Program::try_from(dto).err().unwrap();
+        assert_eq!(error.to_string(), "Card Copy has no instruction for reading off, on");
+    }
+
+    fn find<'a>(programs: &'a [&'a str]) -> impl Fn(&str) -> Result<Option<ProgramDto>, FileErrors> + 'a {
+        move |name| Ok(programs.iter()
+            .map(|yaml| serde_yaml::from_str::<ProgramDto>(yaml).unwrap())
+            .find(|dto| dto.name == name))
+    }
+
+    const SKIP_TWO: &str = "
+name: skip_two
+initial_card: First
+cards:
+  - name: First
+    instruction_on: { write_symbol: null, move_direction: Right, next_card: Second }
+    instruction_off: { write_symbol: null, move_direction: Right, next_card: Second }
+  - name: Second
+    instruction_on: { write_symbol: null, move_direction: Right, next_card: null }
+    instruction_off: { write_symbol: null, move_direction: Right, next_card: null }
+";
+
+    #[test]
+    fn subroutines_are_inlined() {
+        let yaml = "
+name: light_third
+initial_card: Skip
+cards:
+  - name: Skip
+    subroutine: skip_two
+    next_card: Light
+  - name: Light
+    instruction_on: { write_symbol: true, move_direction: null, next_card: null }
+    instruction_off: { write_symbol: true, move_direction: null, next_card: null }
+";
+        let dto: ProgramDto = serde_yaml::from_str(yaml).unwrap();
+        let program = dto.resolve(&find(&[SKIP_TWO])).unwrap();
+        let names: Vec<&str> = program.cards.iter().map(|card| card.name.as_str()).collect();
+        assert_eq!(names, ["Skip", "Skip/Second", "Light"]);
+        assert_eq!(program.cards[1].tape_off.next_card, Some(2));
+
+        let mut execution = TestCaseExecution::new(TestCase::default(), program);
+        assert!(execution.run(10));
+        assert_eq!(execution.positions_on, [2].into_iter().collect());
+    }
+
+    #[test]
+    fn subroutine_cycles_are_errors() {
+        let calls = |name: &str, callee: &str| format!("
+name: {}
+initial_card: Call
+cards:
+  - name: Call
+    subroutine: {}
+", name, callee);
+        let (a, b) = (calls("a", "b"), calls("b", "a"));
+        let dto: ProgramDto = serde_yaml::from_str(&a).unwrap();
+        let error = dto.resolve(&find(&[a.as_str(), b.as_str()])).err().unwrap();
+        assert_eq!(error.to_string(), "Programs run each other as subroutines: a → b → a");
+
+        let dto: ProgramDto = serde_yaml::from_str(&calls("c", "missing")).unwrap();
+        let error = dto.resolve(&find(&[])).err().unwrap();
+        assert_eq!(error.to_string(), "Card Call runs program missing, which was not found");
+    }
+
+    #[test]
+    fn mistakes_next_to_subroutines_are_reported() {
+        let yaml = "
+name: light_third
+initial_card: Skip
+cards:
+  - name: Skip
+    subroutine: skip_too
+    next_card: Lihgt
+  - name: Light
+    instruction_on: { write_symbol: true, move_direction: null, next_card: null }
+";
+        let dto: ProgramDto = serde_yaml::from_str(yaml).unwrap();
+        let errors = dto.resolve(&find(&[SKIP_TWO])).err().unwrap().locate(Path::new("light_third.yaml"), yaml);
+        assert_eq!(errors.to_string(), [
+            "light_third.yaml:5:11: Card Skip runs program skip_too, which was not found",
+            "light_third.yaml:7:16: Card does not exist: Lihgt. Did you mean Light?",
+            "light_third.yaml:8:11: Card Light has no instruction for when the tape is off",
+        ].join("\n"));
+    }
+}
@@ -0,0 +1,722 @@
+
+use crate::file_error::{FileError, FileErrors};
+use crate::program_dto::{CardDto, DirectionDto, InstructionDto, MAX_TAPES, MultiTapeInstructionDto, ProgramDto, TapeActionDto};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum TokenKind {
+    Word(String),
+    Text(String),
+    Colon,
+    Semicolon,
+    Comma,
+    Pipe,
+    Arrow,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: impl Into<String>) -> FileError {
+        FileError::at(self.line, self.column, message)
+    }
+
+    fn describe(&self) -> String {
+        match &self.kind {
+            TokenKind::Word(word) => word.clone(),
+            TokenKind::Text(text) => quote(text),
+            TokenKind::Colon => ":".to_string(),
+            TokenKind::Semicolon => ";".to_string(),
+            TokenKind::Comma => ",".to_string(),
+            TokenKind::Pipe => "|".to_string(),
+            TokenKind::Arrow => "->".to_string(),
+        }
+    }
+
+    /// The word or quoted text of a name
+    fn name(&self) -> Option<String> {
+        match &self.kind {
+            TokenKind::Word(word) | TokenKind::Text(word) => Some(word.clone()),
+            _ => None,
+        }
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word(word.to_string())
+    }
+}
+
+/// The tokens of a line that is not blank
+struct Line {
+    number: usize,
+    /// Whether the line starts with whitespace, which continues the card above it
+    indented: bool,
+    tokens: Vec<Token>,
+    /// Column just after the last character of the line
+    end: usize,
+}
+
+/// Characters that end a word
+const PUNCTUATION: &str = ":;,|\"#";
+
+/// The lines that are not blank, and the mistakes on the lines that could not be read
+fn tokenize(text: &str) -> (Vec<Line>, Vec<FileError>) {
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    'lines: for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let chars: Vec<char> = line.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let column = i + 1;
+            let token = |kind| Token { kind, line: number, column };
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+            } else if c == '#' {
+                break;
+            } else if c == '-' && chars.get(i + 1) == Some(&'>') {
+                tokens.push(token(TokenKind::Arrow));
+                i += 2;
+            } else if let Some(kind) = match c {
+                ':' => Some(TokenKind::Colon),
+                ';' => Some(TokenKind::Semicolon),
+                ',' => Some(TokenKind::Comma),
+                '|' => Some(TokenKind::Pipe),
+                _ => None,
+            } {
+                tokens.push(token(kind));
+                i += 1;
+            } else if c == '"' {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => {
+                            errors.push(FileError::at(number, column, "Text is missing its closing \""));
+                            continue 'lines;
+                        }
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('"') => '"',
+                                Some('\\') => '\\',
+                                _ => {
+                                    errors.push(FileError::at(number, i + 1, "Unknown escape, use \\\", \\\\ or \\n"));
+                                    continue 'lines;
+                                }
+                            };
+                            text.push(escaped);
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            text.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(token(TokenKind::Text(text)));
+                i += 1;
+            } else {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !PUNCTUATION.contains(chars[i]) && (chars[i] != '-' || chars.get(i + 1) != Some(&'>')) {
+                    i += 1;
+                }
+                tokens.push(token(TokenKind::Word(chars[start..i].iter().collect())));
+            }
+        }
+        if !tokens.is_empty() {
+            let indented = chars.first().is_some_and(|c| c.is_whitespace());
+            lines.push(Line { number, indented, tokens, end: chars.len() + 1 });
+        }
+    }
+    (lines, errors)
+}
+
+/// Reads the tokens of a line one at a time
+struct Cursor<'a> {
+    line: &'a Line,
+    index: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.line.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        self.index += 1;
+        token
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().is_none()
+    }
+
+    /// An error at the next token, or at the end of the line
+    fn error(&self, message: impl Into<String>) -> FileError {
+        match self.peek() {
+            Some(token) => token.error(message),
+            None => FileError::at(self.line.number, self.line.end, message),
+        }
+    }
+
+    fn expected(&self, what: &str) -> FileError {
+        match self.peek() {
+            Some(token) => token.error(format!("Expected {} but found {}", what, token.describe())),
+            None => self.error(format!("Expected {} at the end of the line", what)),
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<&'a Token, FileError> {
+        match self.peek() {
+            Some(token) if token.kind == kind => Ok(self.next().unwrap()),
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    fn name(&mut self, what: &str) -> Result<(String, &'a Token), FileError> {
+        match self.peek().and_then(|token| token.name().map(|name| (name, token))) {
+            Some(name) => {
+                self.index += 1;
+                Ok(name)
+            }
+            None => Err(self.expected(what)),
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), FileError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(token.error(format!("Expected the end of the line but found {}", token.describe()))),
+        }
+    }
+}
+
+/// A card as written, before it is checked against the rest of the program
+struct RawCard<'a> {
+    name: String,
+    token: &'a Token,
+    rules: Vec<Rule<'a>>,
+    call: Option<(String, Next<'a>)>,
+    /// Whether a mistake stopped the rules from being read, so that missing ones are not reported
+    is_broken: bool,
+}
+
+/// What a card does when the heads read `read`
+struct Rule<'a> {
+    read: Vec<bool>,
+    read_token: &'a Token,
+    /// What to do on every tape, or `None` when only the next card is given
+    actions: Option<(Vec<TapeActionDto>, &'a Token)>,
+    next: Next<'a>,
+}
+
+/// The card to continue with, `None` to halt
+struct Next<'a> {
+    card: Option<String>,
+    token: &'a Token,
+}
+
+const HEADERS: [&str; 4] = ["program", "description", "tapes", "start"];
+
+/// Parses a program written in the `.tur` text format:
+///
+/// ```text
+/// program move_two
+/// description "Move two cells to the right"
+///
+/// A: on -> R, B; off -> write 1, R, B
+/// B: on -> halt; off -> halt
+/// ```
+///
+/// A card lists what to write, where to move and which card to continue with for each symbol,
+/// separated by commas, or `halt` to stop. The first card is the initial card unless `start`
+/// says otherwise. A symbol with more than one rule makes the program nondeterministic, and a
+/// card written as `A: call other_program, B` runs another program as a subroutine.
+///
+/// Programs with more tapes have `tapes 2` at the top, read a symbol from each tape, and separate
+/// the actions of each tape by `|`, where `-` is a tape left alone. Rules may also go on lines of
+/// their own below the card, indented:
+///
+/// ```text
+/// Copy:
+///     on off -> R | write 1, R, Copy
+///     off off -> halt
+/// ```
+pub fn parse(text: &str) -> Result<ProgramDto, FileErrors> {
+    let (lines, mut errors) = tokenize(text);
+    let mut name: Option<String> = None;
+    let mut description = String::new();
+    let mut tapes: Option<(usize, &Token)> = None;
+    let mut start: Option<(String, &Token)> = None;
+    let mut cards: Vec<RawCard> = Vec::new();
+
+    for line in &lines {
+        let mut cursor = Cursor { line, index: 0 };
+        let first = cursor.next().unwrap();
+        if line.indented {
+            let Some(card) = cards.last_mut() else {
+                errors.push(first.error("Indented rules belong to the card above them, but there is none"));
+                continue;
+            };
+            cursor.index = 0;
+            if let Err(error) = parse_rules(&mut cursor, card) {
+                errors.push(error);
+                card.is_broken = true;
+            }
+            continue;
+        }
+
+        let header = HEADERS.into_iter().find(|header| first.is_word(header));
+        let is_card = cursor.peek().is_some_and(|token| token.kind == TokenKind::Colon);
+        if let (Some(header), false) = (header, is_card) {
+            let result = match header {
+                "program" => cursor.name("the name of the program").map(|(program, _)| name = Some(program)),
+                "description" => match cursor.next() {
+                    Some(Token { kind: TokenKind::Text(text), .. }) => {
+                        description = text.clone();
+                        Ok(())
+                    }
+                    _ => {
+                        cursor.index -= 1;
+                        Err(cursor.expected("the description in quotes"))
+                    }
+                },
+                "tapes" => cursor.name("the amount of tapes").and_then(|(word, token)| {
+                    let count = word.parse::<usize>()
+                        .map_err(|_| token.error(format!("Expected the amount of tapes but found {}", token.describe())))?;
+                    if !(1..=MAX_TAPES).contains(&count) {
+                        return Err(token.error(format!("A program must have between 1 and {} tapes", MAX_TAPES)));
+                    }
+                    tapes = Some((count, token));
+                    Ok(())
+                }),
+                _ => cursor.name("the name of the initial card").map(|initial_card| start = Some(initial_card)),
+            };
+            if let Err(error) = result.and_then(|_| cursor.expect_end()) {
+                errors.push(error);
+            }
+            continue;
+        }
+
+        let Some(card_name) = first.name() else {
+            errors.push(first.error(format!("Expected a card name but found {}", first.describe())));
+            continue;
+        };
+        if cards.iter().any(|card| card.name == card_name) {
+            errors.push(first.error(format!("Duplicate card name: {}", card_name)));
+            continue;
+        }
+        let mut card = RawCard { name: card_name, token: first, rules: Vec::new(), call: None, is_broken: false };
+        if let Err(error) = cursor.expect(TokenKind::Colon, ": after the card name").and_then(|_| parse_rules(&mut cursor, &mut card)) {
+            errors.push(error);
+            card.is_broken = true;
+        }
+        cards.push(card);
+    }
+
+    // A program without a name or cards is only a mistake of its own if no line was unreadable
+    let is_readable = errors.is_empty();
+    if name.is_none() && is_readable {
+        errors.push(FileError::at(1, 1, "The program has no name, start with: program <name>"));
+    }
+    if cards.is_empty() && is_readable {
+        let line = lines.last().map_or(1, |line| line.number);
+        let column = lines.last().map_or(1, |line| line.end);
+        errors.push(FileError::at(line, column, "The program has no cards"));
+    }
+    let tape_count = tapes.map_or(1, |(count, _)| count);
+    let card_names: Vec<&str> = cards.iter().map(|card| card.name.as_str()).collect();
+    let check_card = |card: &str, token: &Token| if card_names.contains(&card) {
+        None
+    } else {
+        Some(token.error(format!("Card does not exist: {}", card)).suggest(card, card_names.iter().copied()))
+    };
+    let check_next = |next: &Next| next.card.as_ref().and_then(|card| check_card(card, next.token));
+    let initial_card = match start {
+        Some((card, token)) => {
+            errors.extend(check_card(&card, token));
+            card
+        }
+        None => cards.first().map(|card| card.name.clone()).unwrap_or_default(),
+    };
+
+    let mut card_dtos = Vec::new();
+    for card in &cards {
+        if let Some((subroutine, next)) = &card.call {
+            errors.extend(check_next(next));
+            card_dtos.push(CardDto {
+                name: card.name.clone(),
+                instruction_on: None,
+                instruction_off: None,
+                instructions: Vec::new(),
+                alternatives: Vec::new(),
+                subroutine: Some(subroutine.clone()),
+                next_card: next.card.clone(),
+            });
+            continue;
+        }
+
+        let mut instructions: Vec<MultiTapeInstructionDto> = Vec::new();
+        let mut alternatives = Vec::new();
+        for rule in &card.rules {
+            errors.extend(check_next(&rule.next));
+            if rule.read.len() != tape_count {
+                errors.push(rule.read_token.error(format!("Expected {} symbols to read, one for every tape, but found {}", tape_count, rule.read.len())));
+                continue;
+            }
+            let actions = match &rule.actions {
+                Some((actions, token)) if actions.len() != tape_count => {
+                    errors.push(token.error(format!("Expected actions for {} tapes, separated by |, but found {}", tape_count, actions.len())));
+                    continue;
+                }
+                Some((actions, _)) => actions.clone(),
+                None => vec![TapeActionDto { write_symbol: None, move_direction: None }; tape_count],
+            };
+            let instruction = MultiTapeInstructionDto { read: rule.read.clone(), tapes: actions, next_card: rule.next.card.clone() };
+            if instructions.iter().any(|other| other.read == instruction.read) {
+                alternatives.push(instruction);
+            } else {
+                instructions.push(instruction);
+            }
+        }
+        for read in 0..1usize << tape_count {
+            let read: Vec<bool> = (0..tape_count).map(|tape| read >> tape & 1 == 1).collect();
+            if !card.is_broken && card.rules.iter().all(|rule| rule.read.len() == tape_count) && !instructions.iter().any(|instruction| instruction.read == read) {
+                errors.push(card.token.error(format!("Card {} has no rule for reading {}", card.name, describe_read(&read))));
+            }
+        }
+
+        card_dtos.push(if tape_count == 1 {
+            let single = |on: bool| instructions.iter()
+                .find(|instruction| instruction.read[0] == on)
+                .map(|instruction| InstructionDto {
+                    write_symbol: instruction.tapes[0].write_symbol,
+                    move_direction: instruction.tapes[0].move_direction,
+                    next_card: instruction.next_card.clone(),
+                });
+            CardDto {
+                name: card.name.clone(),
+                instruction_on: single(true),
+                instruction_off: single(false),
+                instructions: Vec::new(),
+                alternatives,
+                subroutine: None,
+                next_card: None,
+            }
+        } else {
+            CardDto {
+                name: card.name.clone(),
+                instruction_on: None,
+                instruction_off: None,
+                instructions,
+                alternatives,
+                subroutine: None,
+                next_card: None,
+            }
+        });
+    }
+
+    if !errors.is_empty() {
+        errors.sort_by_key(|error| error.location.map(|location| (location.line, location.column)));
+        return Err(errors.into());
+    }
+    Ok(ProgramDto {
+        name: name.unwrap_or_default(),
+        description,
+        initial_card,
+        tapes: tape_count,
+        cards: card_dtos,
+    })
+}
+
+/// Parses the rules of a card separated by `;` until the end of the line
+fn parse_rules<'a>(cursor: &mut Cursor<'a>, card: &mut RawCard<'a>) -> Result<(), FileError> {
+    while !cursor.is_at_end() {
+        if card.call.is_some() {
+            return Err(cursor.error(format!("Card {} calls a program, so it cannot have rules", card.name)));
+        }
+        if cursor.peek().is_some_and(|token| token.is_word("call")) {
+            let call = cursor.next().unwrap();
+            if !card.rules.is_empty() {
+                return Err(call.error(format!("Card {} has rules, so it cannot call a program", card.name)));
+            }
+            let (program, _) = cursor.name("the name of the program to call")?;
+            cursor.expect(TokenKind::Comma, ", and the card to continue with")?;
+            card.call = Some((program, parse_next(cursor)?));
+        } else {
+            card.rules.push(parse_rule(cursor)?);
+        }
+        if !cursor.is_at_end() {
+            cursor.expect(TokenKind::Semicolon, "; between rules")?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_rule<'a>(cursor: &mut Cursor<'a>) -> Result<Rule<'a>, FileError> {
+    let read_token = cursor.peek().ok_or_else(|| cursor.expected("on or off"))?;
+    let mut read = Vec::new();
+    while let Some(token) = cursor.peek() {
+        match &token.kind {
+            TokenKind::Word(word) if word == "on" => read.push(true),
+            TokenKind::Word(word) if word == "off" => read.push(false),
+            _ => break,
+        }
+        cursor.next();
+    }
+    if read.is_empty() {
+        return Err(cursor.expected("on or off"));
+    }
+    cursor.expect(TokenKind::Arrow, "-> after the symbols to read")?;
+
+    // Items separated by commas, with | between the items of different tapes. The last item is
+    // the next card.
+    let actions_token = cursor.peek();
+    let mut actions = vec![TapeActionDto { write_symbol: None, move_direction: None }];
+    let mut has_actions = false;
+    loop {
+        let item_start = cursor.index;
+        while cursor.peek().is_some_and(|token| !matches!(token.kind, TokenKind::Comma | TokenKind::Pipe | TokenKind::Semicolon)) {
+            cursor.next();
+        }
+        let item = &cursor.line.tokens[item_start..cursor.index];
+        match cursor.peek().map(|token| &token.kind) {
+            Some(TokenKind::Comma) | Some(TokenKind::Pipe) => {}
+            _ => {
+                // The last item
+                cursor.index = item_start;
+                if cursor.line.tokens.get(item_start.wrapping_sub(1)).is_some_and(|token| token.kind == TokenKind::Pipe) {
+                    return Err(cursor.error("Expected , before the card to continue with"));
+                }
+                let next = parse_next(cursor)?;
+                let actions = actions_token.filter(|_| has_actions).map(|token| (actions, token));
+                return Ok(Rule { read, read_token, actions, next });
+            }
+        }
+        parse_action(item, cursor, actions.last_mut().unwrap())?;
+        has_actions = true;
+        if cursor.next().unwrap().kind == TokenKind::Pipe {
+            actions.push(TapeActionDto { write_symbol: None, move_direction: None });
+        }
+    }
+}
+
+/// Applies an action written as `write 0`, `write 1`, `L`, `R`, `U`, `D` or `-` to a tape
+fn parse_action(item: &[Token], cursor: &Cursor, action: &mut TapeActionDto) -> Result<(), FileError> {
+    let Some(first) = item.first() else {
+        return Err(cursor.error("Expected an action, use - to leave a tape alone"));
+    };
+    let expected = || first.error(format!("Expected write 0, write 1, L, R, U, D or - but found {}", first.describe()));
+    let TokenKind::Word(word) = &first.kind else {
+        return Err(expected());
+    };
+    let rest = &item[1..];
+    match word.as_str() {
+        "write" => {
+            let symbol = match rest {
+                [Token { kind: TokenKind::Word(symbol), .. }] if symbol == "0" => false,
+                [Token { kind: TokenKind::Word(symbol), .. }] if symbol == "1" => true,
+                [token, ..] => return Err(token.error(format!("Expected 0 or 1 to write but found {}", token.describe()))),
+                [] => return Err(first.error("Expected 0 or 1 after write")),
+            };
+            if action.write_symbol.is_some() {
+                return Err(first.error("The tape is already written to"));
+            }
+            action.write_symbol = Some(symbol);
+            return Ok(());
+        }
+        "-" => {}
+        "L" | "R" | "U" | "D" => {
+            if action.move_direction.is_some() {
+                return Err(first.error("The head already moves"));
+            }
+            action.move_direction = Some(match word.as_str() {
+                "L" => DirectionDto::Left,
+                "R" => DirectionDto::Right,
+                "U" => DirectionDto::Up,
+                _ => DirectionDto::Down,
+            });
+        }
+        _ => return Err(expected()),
+    }
+    match rest.first() {
+        Some(token) => Err(token.error(format!("Expected , but found {}", token.describe()))),
+        None => Ok(()),
+    }
+}
+
+fn parse_next<'a>(cursor: &mut Cursor<'a>) -> Result<Next<'a>, FileError> {
+    let (card, token) = cursor.name("the card to continue with, or halt")?;
+    let card = if token.is_word("halt") { None } else { Some(card) };
+    match cursor.peek() {
+        Some(token) if token.kind != TokenKind::Semicolon => Err(token.error(format!("Expected ; or the end of the line but found {}", token.describe()))),
+        _ => Ok(Next { card, token }),
+    }
+}
+
+fn describe_read(read: &[bool]) -> String {
+    read.iter().map(|&on| if on { "on" } else { "off" }).collect::<Vec<_>>().join(" ")
+}
+
+/// Writes a program in the `.tur` text format, so that `parse` gives back the same program.
+/// Cards of programs with one tape go on a single line, other cards have a line per rule.
+pub fn print(dto: &ProgramDto) -> String {
+    let mut text = format!("program {}\n", name(&dto.name));
+    if !dto.description.is_empty() {
+        text += &format!("description {}\n", quote(&dto.description));
+    }
+    if dto.tapes != 1 {
+        text += &format!("tapes {}\n", dto.tapes);
+    }
+    if dto.cards.first().is_none_or(|card| card.name != dto.initial_card) {
+        text += &format!("start {}\n", name(&dto.initial_card));
+    }
+    for card in &dto.cards {
+        text.push('\n');
+        if let Some(subroutine) = &card.subroutine {
+            text += &format!("{}: call {}, {}", name(&card.name), name(subroutine), next(&card.next_card));
+            continue;
+        }
+        let single = |on: bool, instruction: &InstructionDto| {
+            let action = TapeActionDto { write_symbol: instruction.write_symbol, move_direction: instruction.move_direction };
+            rule(&[on], &[action], &instruction.next_card)
+        };
+        let rules: Vec<String> = [(true, &card.instruction_on), (false, &card.instruction_off)].into_iter()
+            .filter_map(|(on, instruction)| instruction.as_ref().map(|instruction| single(on, instruction)))
+            .chain(card.instructions.iter().chain(&card.alternatives)
+                .map(|instruction| rule(&instruction.read, &instruction.tapes, &instruction.next_card)))
+            .collect();
+        if dto.tapes == 1 {
+            text += &format!("{}: {}", name(&card.name), rules.join("; "));
+        } else {
+            text += &format!("{}:", name(&card.name));
+            rules.iter().for_each(|rule| text += &format!("\n    {}", rule));
+        }
+    }
+    text.push('\n');
+    text
+}
+
+fn rule(read: &[bool], actions: &[TapeActionDto], next_card: &Option<String>) -> String {
+    let describe_action = |action: &TapeActionDto| {
+        let write = action.write_symbol.map(|on| format!("write {}", on as u8));
+        let direction = action.move_direction.map(|direction| match direction {
+            DirectionDto::Left => "L",
+            DirectionDto::Right => "R",
+            DirectionDto::Up => "U",
+            DirectionDto::Down => "D",
+        }.to_string());
+        let items: Vec<String> = write.into_iter().chain(direction).collect();
+        if items.is_empty() { "-".to_string() } else { items.join(", ") }
+    };
+    let does_nothing = actions.iter().all(|action| action.write_symbol.is_none() && action.move_direction.is_none());
+    let actions = if does_nothing {
+        String::new()
+    } else if actions.len() == 1 {
+        // A single tape left alone needs no -
+        format!("{}, ", describe_action(&actions[0]))
+    } else {
+        format!("{}, ", actions.iter().map(describe_action).collect::<Vec<_>>().join(" | "))
+    };
+    format!("{} -> {}{}", describe_read(read), actions, next(next_card))
+}
+
+fn next(card: &Option<String>) -> String {
+    card.as_ref().map_or("halt".to_string(), |card| name(card))
+}
+
+/// Words that would be read as something else than a name
+const KEYWORDS: [&str; 7] = ["halt", "call", "write", "on", "off", "-", ""];
+
+/// A name as a word if possible, or otherwise in quotes
+fn name(name: &str) -> String {
+    let is_word = name.chars().all(|c| !c.is_whitespace() && !PUNCTUATION.contains(c)) && !name.contains("->");
+    if is_word && !KEYWORDS.contains(&name) {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::program::Program;
+    use crate::program_dto::ProgramDto;
+    use crate::program_text::{parse, print};
+    use crate::programs;
+
+    #[test]
+    fn round_trip_builtins() {
+        for program in programs::builtins() {
+            let dto = ProgramDto::from(program);
+            let text = print(&dto);
+            assert_eq!(parse(&text), Ok(dto), "{}", text);
+        }
+    }
+
+    #[test]
+    fn round_trip_text() {
+        let text = r#"program "guess and call"
+description "Quotes \" and\nnew lines"
+start Guess
+
+"Light right": on -> halt; off -> write 1, R, Guess
+Guess: on -> halt; off -> L, Guess; off -> R, "Light right"; off -> U, halt
+Call: call move8_solution, halt
+"#;
+        let dto = parse(text).unwrap();
+        assert_eq!(print(&dto), text);
+        assert_eq!(dto.cards[1].alternatives.len(), 2);
+        assert_eq!(dto.cards[2].subroutine.as_deref(), Some("move8_solution"));
+    }
+
+    #[test]
+    fn parses_cards_on_one_line_or_several() {
+        let one_line = "program move\nA: on -> write 0, R, B; off -> write 1, R, B\nB: on -> halt; off -> halt";
+        let several = "program move\n# The same, one rule per line\nA:\n  off -> write 1, R, B\n  on -> write 0, R, B\nB: on -> halt\n  off -> halt";
+        let a = Program::try_from(parse(one_line).unwrap()).unwrap();
+        let b = Program::try_from(parse(several).unwrap()).unwrap();
+        assert_eq!(print(&ProgramDto::from(a)), print(&ProgramDto::from(b)));
+    }
+
+    #[test]
+    fn parses_more_tapes() {
+        let text = "program copy\ntapes 2\n\nCopy:\n    off off -> halt\n    on off -> R | write 1, R, Copy\n    off on -> halt\n    on on -> - | R, Copy\n";
+        let program = Program::try_from(parse(text).unwrap()).unwrap();
+        assert_eq!(program.tapes(), 2);
+        assert_eq!(program.cards[0].instruction(0b01).other_tapes[0].write_symbol, Some(true));
+        assert_eq!(print(&ProgramDto::from(program)), text);
+    }
+
+    #[test]
+    fn errors_point_at_the_mistake() {
+        let error = |text: &str| parse(text).unwrap_err().errors.iter()
+            .map(|error| format!("{}:{} {}", error.location.unwrap().line, error.location.unwrap().column, error.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(error("program p\nA: on -> halt; off -> R, B"), "2:26 Card does not exist: B");
+        assert_eq!(error("program p\nA: on halt"), "2:7 Expected -> after the symbols to read but found halt");
+        assert_eq!(error("program p\nA: on -> jump, A; off -> halt"), "2:10 Expected write 0, write 1, L, R, U, D or - but found jump");
+        assert_eq!(error("program p\nA: on -> halt"), "2:1 Card A has no rule for reading off");
+        assert_eq!(error("program p\ntapes 2\nA: on -> halt"), "3:4 Expected 2 symbols to read, one for every tape, but found 1");
+        assert_eq!(error("program p\ntapes 70\nA: on -> halt; off -> halt"), "2:7 A program must have between 1 and 8 tapes");
+        assert_eq!(error("program p\ntapes 0\nA: on -> halt; off -> halt"), "2:7 A program must have between 1 and 8 tapes");
+        assert_eq!(error("program p\nA: on -> R L, A"), "2:12 Expected , but found L");
+        assert_eq!(error("program p\nA: on -> R, L, A"), "2:13 The head already moves");
+        assert_eq!(error("A: on -> halt; off -> halt"), "1:1 The program has no name, start with: program <name>");
+        assert_eq!(error("program \"p"), "1:9 Text is missing its closing \"");
+    }
+}
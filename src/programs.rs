@@ -1,4 +1,6 @@
+use crate::compiler;
 use crate::program::{Card, Direction, Instruction, Program};
+use crate::program_dto::ProgramDto;
 
 pub fn builtins() -> Vec<Program> {
     vec![
@@ -7,9 +9,76 @@ pub fn builtins() -> Vec<Program> {
         light_left(),
         go_right(),
         light_the_world(),
+        night_time_solution(),
+        moth_solution(),
+        move8_solution(),
+        increment_solution(),
+        addition_solution(),
+        binary_increment_solution(),
+        copy_solution(),
+        box_solution(),
+        langtons_ant(),
+        lucky_moth_solution(),
+        ring_solution(),
+        unary_addition(),
     ]
 }
 
+fn from_yaml(yaml: &str) -> Program {
+    serde_yaml::from_str::<ProgramDto>(yaml)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn night_time_solution() -> Program {
+    from_yaml(include_str!("../res/program/night_time_solution.yaml"))
+}
+
+fn moth_solution() -> Program {
+    from_yaml(include_str!("../res/program/moth_solution.yaml"))
+}
+
+fn move8_solution() -> Program {
+    from_yaml(include_str!("../res/program/move8_solution.yaml"))
+}
+
+fn increment_solution() -> Program {
+    from_yaml(include_str!("../res/program/increment_solution.yaml"))
+}
+
+fn addition_solution() -> Program {
+    from_yaml(include_str!("../res/program/addition_solution.yaml"))
+}
+
+fn unary_addition() -> Program {
+    compiler::compile(include_str!("../res/program/unary_addition.tl")).unwrap()
+}
+
+fn binary_increment_solution() -> Program {
+    from_yaml(include_str!("../res/program/binary_increment_solution.yaml"))
+}
+
+fn copy_solution() -> Program {
+    from_yaml(include_str!("../res/program/copy_solution.yaml"))
+}
+
+fn box_solution() -> Program {
+    from_yaml(include_str!("../res/program/box_solution.yaml"))
+}
+
+fn langtons_ant() -> Program {
+    from_yaml(include_str!("../res/program/langtons_ant.yaml"))
+}
+
+fn lucky_moth_solution() -> Program {
+    from_yaml(include_str!("../res/program/lucky_moth_solution.yaml"))
+}
+
+fn ring_solution() -> Program {
+    from_yaml(include_str!("../res/program/ring_solution.yaml"))
+}
+
 fn just_stop() -> Program {
     let card = Card::terminate();
     Program {
@@ -57,12 +126,16 @@ fn light_the_world() -> Program {
             write_symbol: None,
             move_direction: Some(Direction::Left),
             next_card: Some(0),
+            other_tapes: Vec::new(),
         },
         tape_off: Instruction {
             write_symbol: Some(true),
             move_direction: Some(Direction::Right),
             next_card: Some(1),
+            other_tapes: Vec::new(),
         },
+        other_instructions: Vec::new(),
+        alternatives: Vec::new(),
     };
     let right = Card {
         name: "RIGHT".to_string(),
@@ -70,12 +143,16 @@ fn light_the_world() -> Program {
             write_symbol: None,
             move_direction: Some(Direction::Right),
             next_card: Some(1),
+            other_tapes: Vec::new(),
         },
         tape_off: Instruction {
             write_symbol: Some(true),
             move_direction: Some(Direction::Left),
             next_card: Some(0),
+            other_tapes: Vec::new(),
         },
+        other_instructions: Vec::new(),
+        alternatives: Vec::new(),
     };
     Program {
         name: "light_the_world".to_string(),
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Progress {
+    #[serde(default)]
+    levels: BTreeMap<String, LevelProgress>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct LevelProgress {
+    #[serde(default)]
+    pub solved: bool,
+    #[serde(default)]
+    pub hints_used: usize,
+}
+
+impl Progress {
+    pub fn get(&self, level_name: &str) -> LevelProgress {
+        self.levels.get(level_name).cloned().unwrap_or_default()
+    }
+
+    pub fn is_solved(&self, level_name: &str) -> bool {
+        self.get(level_name).solved
+    }
+
+    /// Reveals one more hint, if there are any left, and returns how many have been revealed.
+    pub fn use_hint(&mut self, level_name: &str, hint_count: usize) -> usize {
+        let progress = self.levels.entry(level_name.to_string()).or_default();
+        progress.hints_used = (progress.hints_used + 1).min(hint_count);
+        progress.hints_used
+    }
+
+    pub fn set_solved(&mut self, level_name: &str) {
+        self.levels.entry(level_name.to_string()).or_default().solved = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::progress::Progress;
+
+    #[test]
+    fn hints_are_recorded_alongside_solves() {
+        let mut progress = Progress::default();
+        assert_eq!(progress.use_hint("moth", 2), 1);
+        assert_eq!(progress.use_hint("moth", 2), 2);
+        assert_eq!(progress.use_hint("moth", 2), 2);
+        progress.set_solved("moth");
+
+        let yaml = serde_yaml::to_string(&progress).unwrap();
+        let progress: Progress = serde_yaml::from_str(&yaml).unwrap();
+        assert!(progress.is_solved("moth"));
+        assert_eq!(progress.get("moth").hints_used, 2);
+        assert_eq!(progress.get("night_time").hints_used, 0);
+    }
+}
@@ -0,0 +1,23 @@
+use std::collections::HashSet;
+
+/// Small deterministic xorshift generator, so generated inputs can be reproduced.
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random(seed.max(1))
+    }
+
+    /// A number in `0..bound`
+    pub fn next(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+
+    /// A tape where each cell in `-radius..=radius` is lit with probability one in three
+    pub fn tape(&mut self, radius: i64) -> HashSet<i64> {
+        (-radius..=radius).filter(|_| self.next(3) == 0).collect()
+    }
+}
@@ -1,111 +1,195 @@
 use std::fmt::Debug;
 
-use anyhow::Result;
-use console::Term;
-
-use crate::execution::{LevelExecution, TestCaseExecution, TestCaseExecutionState};
-
-const WINDOW_SIZE: i64 = 64;
-const WINDOW_OFFSET: i64 = 32;
-
-pub fn render(term: &Term, le: &LevelExecution) -> Result<()> {
-    term.clear_screen()?;
-    term.write_line(&format!("Level: {}", le.level.name))?;
-    term.write_line(&format!("Program: {}", le.program.name))?;
-    term.write_line("Test cases:")?;
-    for (index, tce) in le.executions.iter().enumerate() {
-        match tce.get_state() {
-            TestCaseExecutionState::Pending => {
-                term.write_line(&format!("{:>4}: Pending", index))?;
-            }
-            TestCaseExecutionState::Running => {
-                term.write_line(&format!("{:>4}: Running", index))?;
-                term.write_line(&format!("      Steps: {}", tce.steps))?;
-            }
-            TestCaseExecutionState::Success => {
-                term.write_line(&format!("{:>4}: Success", index))?;
-                term.write_line(&format!("      Steps: {}", tce.steps))?;
-            }
-            TestCaseExecutionState::Failure { errors } => {
-                term.write_line(&format!("{:>4}: Failure", index))?;
-                for error in errors {
-                    term.write_line(&format!("       - {}", error))?;
-                }
-            }
-        }
-    }
-    if let Some(tce) = le.current_execution() {
-        render_tce(term, tce)?;
-    }
-    if le.is_terminated() {
-        term.write_line(&format!("Total steps: {}", le.get_steps()))?;
-    }
+use crate::execution::{Grid, TestCaseExecution};
+use crate::level::Topology;
+
+/// Rows of a grid shown above and below the head
+const GRID_ROWS: i64 = 4;
+/// Columns next to the cells, for the row numbers of a grid and the marker of a head past the end
+const MARGIN: usize = 6;
+/// Fewest cells shown, however narrow the terminal
+const MIN_CELLS: i64 = 16;
+/// Cells between two ticks
+const TICK: i64 = 16;
 
-    Ok(())
+/// Which cells of the tapes are shown. By default the view jumps by half its width whenever the
+/// head on the first tape leaves it. Either end can be pinned to a position, and a pinned range
+/// too wide for the terminal scrolls with the head within it.
+#[derive(Copy, Clone, Default)]
+pub struct TapeView {
+    /// Scroll with every step to keep the head in the middle, instead of jumping
+    pub follow: bool,
+    /// First cell that can be shown
+    pub from: Option<i64>,
+    /// Last cell that can be shown
+    pub to: Option<i64>,
 }
 
-pub fn render_tce(term: &Term, tce: &TestCaseExecution) -> Result<()> {
-    let pos = tce.get_current_position();
-    let from = from(pos);
-    let to = from + WINDOW_SIZE + 1;
+impl TapeView {
+    /// The cells `from..to` shown in `width` columns when the head is at `pos`
+    pub fn window(&self, pos: i64, width: usize) -> (i64, i64) {
+        let cells = (width.saturating_sub(MARGIN) as i64).max(MIN_CELLS);
+        let first = self.from.unwrap_or(i64::MIN);
+        let last = self.to.unwrap_or(i64::MAX).max(first);
+        if last.checked_sub(first).is_some_and(|span| span < cells) {
+            return (first, last.saturating_add(1));
+        }
+        let start = if self.follow {
+            pos.saturating_sub(cells / 2)
+        } else {
+            from(pos, cells - 1)
+        };
+        let start = start.clamp(first, last - (cells - 1));
+        (start, start.saturating_add(cells))
+    }
+}
 
-    // Render position
-    let position_line = " ".repeat((pos - from) as usize) + "v";
-    term.write_line(&position_line)?;
+/// The tapes of a test case in `width` columns, below each other, with the heads marked above them
+/// and ticks with positions below them. The `touched` cells of the first tape are underlined.
+pub fn tape_lines(tce: &TestCaseExecution, view: &TapeView, width: usize, touched: Option<(i64, i64)>) -> Vec<String> {
+    let (from, to) = view.window(tce.get_current_position(), width);
+    let mut lines = Vec::new();
+    let touched_line = |lines: &mut Vec<String>| {
+        if let Some((first, last)) = touched {
+            lines.push((from..to).map(|i| if (first..=last).contains(&i) { '‾' } else { ' ' }).collect());
+        }
+    };
 
-    // Render tape
-    let mut tape_line = String::new();
-    for i in from..to {
-        if tce.get_tape_at(i) {
-            tape_line.push('■')
+    // The position and the tape of every tape, below each other
+    let first_tape = match tce.get_grid() {
+        Some(grid) => {
+            lines.extend(grid_lines(grid, from, to));
+            touched_line(&mut lines);
+            1
+        }
+        None => 0,
+    };
+    for tape in first_tape..tce.get_tape_count() {
+        let head = tce.get_current_position_on(tape);
+        let position_line = if head < from {
+            "<".to_string()
+        } else if head >= to {
+            " ".repeat((to - from) as usize) + ">"
         } else {
-            tape_line.push('□')
+            " ".repeat((head - from) as usize) + "v"
+        };
+        lines.push(position_line);
+
+        // Cells that are not on the tape are blank, and a circular tape repeats around the window
+        let topology = if tape == 0 { tce.get_topology() } else { Topology::Line };
+        let mut tape_line = String::new();
+        for i in from..to {
+            let cell = match topology {
+                Topology::Circular { .. } => topology.place(i),
+                _ => Some(i).filter(|&i| topology.contains(i)),
+            };
+            match cell {
+                None => tape_line.push(' '),
+                Some(cell) if tce.get_tape_at_on(tape, cell) => tape_line.push('■'),
+                Some(_) => tape_line.push('□'),
+            }
+        }
+        lines.push(tape_line);
+        if tape == 0 {
+            touched_line(&mut lines);
         }
     }
-    term.write_line(&tape_line)?;
 
-    // Render ticks
-    let mut tick_line = String::new();
-    for _ in 0..=WINDOW_SIZE / 16 {
-        tick_line += &format!("{:<16}", '|');
-    }
-    term.write_line(&tick_line)?;
+    // Ticks at the same positions wherever the window is, with the position below each tick
+    let tick_line: String = (from..to).map(|i| if i.rem_euclid(TICK) == 0 { '|' } else { ' ' }).collect();
+    lines.push(tick_line);
     let mut number_line = String::new();
-    for n in 0..=WINDOW_SIZE / 16 {
-        number_line += &format!("{:<16}", from + n * 16);
-    }
-    term.write_line(&number_line)?;
-
-    if let Some(card) = tce.get_current_card() {
-        term.write_line(&format!("Current card: {}", card.name))?;
+    for i in (from..to).filter(|i| i.rem_euclid(TICK) == 0) {
+        // Positions too wide to fit before the next tick are left out
+        let column = (i - from) as usize;
+        let gap = column.checked_sub(number_line.len()).filter(|&gap| gap > 0 || number_line.is_empty());
+        if let Some(gap) = gap {
+            number_line += &" ".repeat(gap);
+            number_line += &i.to_string();
+        }
     }
+    lines.push(number_line);
+    lines
+}
 
-    Ok(())
+/// The rows of a grid around the head, with the head marked in its cell and the row number at the
+/// end of each row.
+fn grid_lines(grid: &Grid, from: i64, to: i64) -> Vec<String> {
+    let (_, head_y) = grid.position;
+    (head_y - GRID_ROWS..=head_y + GRID_ROWS)
+        .map(|y| {
+            let row_line: String = (from..to)
+                .map(|x| match ((x, y) == grid.position, grid.cells_on.contains(&(x, y))) {
+                    (true, true) => '◆',
+                    (true, false) => '◇',
+                    (false, true) => '■',
+                    (false, false) => '□',
+                })
+                .collect();
+            format!("{} {}", row_line, y)
+        })
+        .collect()
 }
 
-fn from(pos: i64) -> i64 {
-    let effective_position = pos + WINDOW_OFFSET;
-    if effective_position < 0 && effective_position % WINDOW_SIZE != 0 {
-        (effective_position / WINDOW_SIZE - 1) * WINDOW_SIZE - WINDOW_OFFSET
-    } else {
-        effective_position / WINDOW_SIZE * WINDOW_SIZE - WINDOW_OFFSET
-    }
+/// First cell of the page of `size` cells that shows `pos`, where pages start half a page before
+/// multiples of `size`
+fn from(pos: i64, size: i64) -> i64 {
+    let offset = size / 2;
+    pos.saturating_add(offset).div_euclid(size).saturating_mul(size).saturating_sub(offset)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::render::{from, WINDOW_OFFSET, WINDOW_SIZE};
+    use crate::execution::TestCaseExecution;
+    use crate::render::{from, tape_lines, TapeView};
+    use crate::{levels, programs};
 
     #[test]
     fn test_from() {
-        for n in -2..3 {
-            let expected = -WINDOW_OFFSET + WINDOW_SIZE * n;
-            for m in vec![0, -1, 1, -WINDOW_OFFSET, -WINDOW_OFFSET + WINDOW_SIZE - 1] {
-                let pos = m + WINDOW_SIZE * n;
-                let actual = from(pos);
-                assert_eq!(actual, expected, "Expected from({}) to be {}, but was {}", pos, expected, actual);
+        for size in [64, 73, 100] {
+            let offset = size / 2;
+            for n in -2..3 {
+                let expected = -offset + size * n;
+                for m in [0, -1, 1, -offset, -offset + size - 1] {
+                    let pos = m + size * n;
+                    let actual = from(pos, size);
+                    assert_eq!(actual, expected, "Expected from({}, {}) to be {}, but was {}", pos, size, expected, actual);
+                }
             }
         }
     }
-}
 
+    #[test]
+    fn windows_fit_the_width_and_follow_the_head() {
+        let view = TapeView::default();
+        assert_eq!(view.window(0, 71), (-32, 33));
+        assert_eq!(view.window(33, 71), (32, 97));
+
+        let follow = TapeView { follow: true, ..TapeView::default() };
+        assert_eq!(follow.window(33, 46), (13, 53));
+
+        let pinned = TapeView { from: Some(-5), to: Some(5), ..follow };
+        assert_eq!(pinned.window(100, 46), (-5, 6));
+        assert_eq!(TapeView { to: None, ..pinned }.window(-100, 46), (-5, 35));
+        assert_eq!(TapeView { to: None, ..pinned }.window(100, 46), (80, 120));
+        assert_eq!(TapeView { from: None, ..pinned }.window(100, 46), (-34, 6));
+
+        let wide = TapeView { from: Some(0), to: Some(i64::MAX), follow: false };
+        assert_eq!(wide.window(0, 71), (0, 65));
+        assert_eq!(TapeView { follow: true, ..wide }.window(i64::MAX, 71), (i64::MAX - 64, i64::MAX));
+    }
+
+    #[test]
+    fn positions_that_do_not_fit_are_left_out() {
+        let level = levels::builtins().into_iter().find(|level| level.name == "addition").unwrap();
+        let program = programs::builtins().into_iter().find(|program| program.name == "addition_solution").unwrap();
+        let tce = TestCaseExecution::new(level.cases[0].clone(), program);
+        let view = TapeView { from: Some(-100_000_000_000_000_000), to: Some(-100_000_000_000_000_000 + 40), follow: false };
+
+        let lines = tape_lines(&tce, &view, 80, None);
+        let numbers = lines.last().unwrap();
+        assert!(numbers.starts_with("-100000000000000000 "), "{}", numbers);
+        assert!(!numbers.contains("-99999999999999984"), "{}", numbers);
+        assert!(numbers.split_whitespace().all(|number| number.parse::<i64>().is_ok()), "{}", numbers);
+    }
+}
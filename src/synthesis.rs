@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+
+use crate::execution::{TestCaseExecution, TestCaseExecutionState};
+use crate::level::{Level, TestCase};
+use crate::parallel;
+use crate::program::{Card, Direction, Instruction, Program};
+
+pub struct Solution {
+    pub program: Program,
+    /// Total amount of steps over all test cases
+    pub steps: u64,
+}
+
+/// Finds every program with at most `max_cards` cards that solves `level`, fewest cards first.
+///
+/// Programs are enumerated in tree-normal form: an instruction is only chosen once some test case
+/// reaches it, and a card is only introduced as the next card after all lower numbered cards. This
+/// skips instructions that no test case executes as well as renumberings of the same program.
+pub fn solve(level: &Level, max_cards: usize, max_steps: u64) -> Vec<Solution> {
+    solve_parallel(level, max_cards, max_steps, 1)
+}
+
+/// Amount of parts the search is split into before the parts are divided over the threads.
+/// This does not depend on the amount of threads, so neither does the order of the solutions.
+const PARTS: usize = 64;
+
+/// Like `solve`, but searches on up to `jobs` threads. The solutions are in the same order
+/// for any amount of threads.
+pub fn solve_parallel(level: &Level, max_cards: usize, max_steps: u64, jobs: usize) -> Vec<Solution> {
+    let Some(tc) = level.cases.first() else {
+        return Vec::new();
+    };
+    (1..=max_cards)
+        .flat_map(|cards| {
+            let mut search = Search::new(level, cards, max_steps);
+            let slots = vec![[None, None]; cards];
+            let mut tasks = vec![Task {
+                run: CaseRun::new(tc, to_program(&slots)),
+                slots,
+                used_cards: 1,
+                case: 0,
+                steps: 0,
+            }];
+            while !tasks.is_empty() && tasks.len() < PARTS {
+                tasks = tasks.into_iter().flat_map(|task| search.expand(task)).collect();
+            }
+            let mut solutions = search.solutions;
+            let parts = parallel::map(tasks, jobs, |task| {
+                let mut search = Search::new(level, cards, max_steps);
+                search.search(task);
+                search.solutions
+            });
+            solutions.extend(parts.into_iter().flatten());
+            solutions
+        })
+        .collect()
+}
+
+pub fn card_name(index: usize) -> String {
+    let letter = (b'A' + (index % 26) as u8) as char;
+    if index < 26 {
+        letter.to_string()
+    } else {
+        format!("{}{}", letter, index / 26)
+    }
+}
+
+/// Instructions of a partial program, indexed by card and then by whether the tape is on.
+type Slots = Vec<[Option<Instruction>; 2]>;
+
+enum Run {
+    Success { steps: u64 },
+    Failure,
+    Undefined { card: usize, on: bool },
+}
+
+/// A test case execution together with the state of its cycle detection.
+#[derive(Clone)]
+struct CaseRun {
+    tce: TestCaseExecution,
+    snapshot: Option<(usize, i64, HashSet<i64>)>,
+    checkpoint: u64,
+}
+
+impl CaseRun {
+    fn new(tc: &TestCase, program: Program) -> CaseRun {
+        CaseRun {
+            tce: TestCaseExecution::new(tc.clone(), program),
+            snapshot: None,
+            checkpoint: 1,
+        }
+    }
+}
+
+/// A partial program, and how far its test cases have been run with it.
+struct Task {
+    slots: Slots,
+    used_cards: usize,
+    /// Test case that `run` is running. Earlier cases already succeed without using any of the
+    /// instructions that are still undefined.
+    case: usize,
+    run: CaseRun,
+    /// Steps taken by the earlier test cases
+    steps: u64,
+}
+
+struct Search<'a> {
+    level: &'a Level,
+    cards: usize,
+    max_steps: u64,
+    solutions: Vec<Solution>,
+}
+
+impl Search<'_> {
+    fn new(level: &Level, cards: usize, max_steps: u64) -> Search<'_> {
+        Search {
+            level,
+            cards,
+            max_steps,
+            solutions: Vec::new(),
+        }
+    }
+
+    fn search(&mut self, task: Task) {
+        for task in self.expand(task) {
+            self.search(task);
+        }
+    }
+
+    /// Resumes the run of the task, then runs the remaining test cases, until an instruction is
+    /// needed that has not been chosen yet. Returns a task for every choice of that instruction.
+    fn expand(&mut self, task: Task) -> Vec<Task> {
+        let Task { slots, used_cards, mut case, mut run, mut steps } = task;
+        loop {
+            match self.run(&mut run, &slots) {
+                Run::Success { steps: case_steps } => steps += case_steps,
+                Run::Failure => return Vec::new(),
+                Run::Undefined { card, on } => {
+                    return self.options(card, on, used_cards).into_iter()
+                        .map(|instruction| {
+                            let used_cards = if instruction.next_card == Some(used_cards) { used_cards + 1 } else { used_cards };
+                            let mut run = run.clone();
+                            let program_card = &mut run.tce.get_program_mut().cards[card];
+                            if on {
+                                program_card.tape_on = instruction.clone();
+                            } else {
+                                program_card.tape_off = instruction.clone();
+                            }
+                            let mut slots = slots.clone();
+                            slots[card][on as usize] = Some(instruction);
+                            Task { slots, used_cards, case, run, steps }
+                        })
+                        .collect();
+                }
+            }
+            case += 1;
+            match self.level.cases.get(case) {
+                Some(tc) => run = CaseRun::new(tc, to_program(&slots)),
+                None => break,
+            }
+        }
+        if used_cards == self.cards {
+            self.solutions.push(Solution { program: to_program(&slots), steps });
+        }
+        Vec::new()
+    }
+
+    fn options(&self, card: usize, on: bool, used_cards: usize) -> Vec<Instruction> {
+        let mut options = Vec::new();
+        let next_cards = std::iter::once(None).chain((0..(used_cards + 1).min(self.cards)).map(Some));
+        for next_card in next_cards {
+            for move_direction in [None, Some(Direction::Left), Some(Direction::Right)] {
+                for write_symbol in [None, Some(!on)] {
+                    if next_card == Some(card) && move_direction.is_none() && write_symbol.is_none() {
+                        continue;
+                    }
+                    options.push(Instruction {
+                        write_symbol,
+                        move_direction,
+                        next_card,
+                        other_tapes: Vec::new(),
+                    });
+                }
+            }
+        }
+        options
+    }
+
+    /// Runs a test case until it halts, exceeds the step limit, repeats a configuration,
+    /// or reaches an instruction that has not been chosen yet.
+    fn run(&self, run: &mut CaseRun, slots: &Slots) -> Run {
+        let tce = &mut run.tce;
+        while let Some(card) = tce.get_current_card_index() {
+            let position = tce.get_current_position();
+            let on = tce.get_tape_at(position);
+            if slots[card][on as usize].is_none() {
+                return Run::Undefined { card, on };
+            }
+            if tce.steps >= self.max_steps || !on && runs_away(tce, slots, card) {
+                return Run::Failure;
+            }
+            if let Some((snapshot_card, snapshot_position, snapshot_tape)) = &run.snapshot {
+                if *snapshot_card == card && *snapshot_position == position && *snapshot_tape == tce.positions_on {
+                    return Run::Failure;
+                }
+            }
+            if tce.steps == run.checkpoint {
+                run.snapshot = Some((card, position, tce.positions_on.clone()));
+                run.checkpoint *= 2;
+            }
+            tce.step();
+        }
+        match tce.get_state() {
+            TestCaseExecutionState::Success => Run::Success { steps: tce.steps },
+            _ => Run::Failure,
+        }
+    }
+}
+
+/// Whether the head is on blank tape that extends forever in some direction, and the
+/// instructions for unlit cells keep moving it that way without writing or halting.
+fn runs_away(tce: &TestCaseExecution, slots: &Slots, card: usize) -> bool {
+    let position = tce.get_current_position();
+    let directions = [
+        (Direction::Left, tce.positions_on.iter().all(|&lit| lit > position)),
+        (Direction::Right, tce.positions_on.iter().all(|&lit| lit < position)),
+    ];
+    directions.into_iter()
+        .filter(|(_, blank)| *blank)
+        .any(|(direction, _)| {
+            let mut visited = HashSet::from([card]);
+            let mut card = card;
+            while let Some(instruction) = &slots[card][0] {
+                let moves_on = matches!((instruction.move_direction, direction), (Some(Direction::Left), Direction::Left) | (Some(Direction::Right), Direction::Right));
+                match instruction.next_card {
+                    Some(next) if moves_on && instruction.write_symbol != Some(true) => {
+                        if !visited.insert(next) {
+                            return true;
+                        }
+                        card = next;
+                    }
+                    _ => return false,
+                }
+            }
+            false
+        })
+}
+
+fn to_program(slots: &Slots) -> Program {
+    let halt = Instruction {
+        write_symbol: None,
+        move_direction: None,
+        next_card: None,
+        other_tapes: Vec::new(),
+    };
+    let cards = slots.iter()
+        .enumerate()
+        .map(|(index, [off, on])| Card {
+            name: card_name(index),
+            tape_on: on.clone().unwrap_or(halt.clone()),
+            tape_off: off.clone().unwrap_or(halt.clone()),
+            other_instructions: Vec::new(),
+            alternatives: Vec::new(),
+        })
+        .collect();
+    Program {
+        name: String::new(),
+        description: String::new(),
+        initial_card: 0,
+        cards,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution::LevelExecution;
+    use crate::level::Level;
+    use crate::levels;
+    use crate::program_dto::ProgramDto;
+    use crate::synthesis::{solve, solve_parallel, Solution};
+
+    fn find_level(name: &str) -> Level {
+        levels::builtins().into_iter().find(|level| level.name == name).unwrap()
+    }
+
+    #[test]
+    fn solutions_solve_the_level() {
+        for name in ["night_time", "increment", "binary_increment"] {
+            let level = find_level(name);
+            let solutions = solve(&level, 1, 1000);
+            assert!(!solutions.is_empty(), "No solution found for {}", name);
+            for solution in solutions {
+                let mut execution = LevelExecution::new(level.clone(), solution.program);
+                execution.run(1000);
+                assert!(execution.is_solved());
+                assert_eq!(execution.get_steps(), solution.steps);
+            }
+        }
+    }
+
+    #[test]
+    fn every_card_is_used() {
+        let solutions = solve(&find_level("increment"), 2, 100);
+        assert!(!solutions.is_empty());
+        for solution in solutions {
+            let cards = solution.program.cards.len();
+            for index in 1..cards {
+                let referenced = solution.program.cards.iter()
+                    .any(|card| card.tape_on.next_card == Some(index) || card.tape_off.next_card == Some(index));
+                assert!(referenced);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_search_finds_the_same_solutions() {
+        let level = find_level("increment");
+        let describe = |solutions: Vec<Solution>| -> Vec<String> {
+            solutions.into_iter()
+                .map(|solution| format!("{} {}", serde_yaml::to_string(&ProgramDto::from(solution.program)).unwrap(), solution.steps))
+                .collect()
+        };
+        let sequential = describe(solve(&level, 2, 100));
+        assert!(!sequential.is_empty());
+        assert_eq!(describe(solve_parallel(&level, 2, 100, 4)), sequential);
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+
+use anyhow::Result;
+use console::{measure_text_width, pad_str, style, truncate_str, Alignment, Term};
+
+use crate::execution::{Cell, ExecutionObserver, LevelExecution, Step, TestCaseExecution, TestCaseExecutionState};
+use crate::program::{Direction, Instruction, Program};
+use crate::render::{tape_lines, TapeView};
+
+/// Most steps the step log remembers
+const LOG_SIZE: usize = 256;
+/// Fewest lines of the step log, which gets whatever the other panes leave over
+const LOG_ROWS: usize = 4;
+const SEPARATOR: &str = " │ ";
+
+/// A full-screen view of a level execution with the program, the test cases, the tape and a log of
+/// the last steps. Only lines that changed since the previous frame are redrawn, so the view
+/// neither flickers nor scrolls.
+pub struct Tui {
+    term: Term,
+    log: StepLog,
+    view: TapeView,
+    /// The lines on the screen, to compare the next frame with
+    screen: Vec<String>,
+    size: (u16, u16),
+}
+
+impl Tui {
+    pub fn new(term: Term, program: &Program, view: TapeView) -> Tui {
+        Tui {
+            term,
+            log: StepLog::new(program.clone()),
+            view,
+            screen: Vec::new(),
+            size: (0, 0),
+        }
+    }
+
+    /// Executes a step of the execution and logs it
+    pub fn step(&mut self, execution: &mut LevelExecution) {
+        let _ = execution.step_observed(&mut self.log);
+    }
+
+    /// Runs the execution without drawing until it terminates or the observer stops it, logging
+    /// the steps on the way. Returns whether all test cases terminated.
+    pub fn run_until(&mut self, execution: &mut LevelExecution, max_steps: u64, observer: &mut dyn ExecutionObserver) -> bool {
+        execution.run_observed(max_steps, &mut (&mut self.log, observer))
+    }
+
+    /// Draws the execution over the previous frame, and leaves the cursor below it
+    pub fn draw(&mut self, execution: &LevelExecution) -> Result<()> {
+        if !self.term.is_term() {
+            for line in frame(execution, &self.log, &self.view, 80, 40) {
+                self.term.write_line(line.trim_end())?;
+            }
+            return Ok(());
+        }
+        let size = self.term.size();
+        if size != self.size {
+            self.term.clear_screen()?;
+            self.screen.clear();
+            self.size = size;
+        }
+        let (rows, columns) = size;
+        // The last row stays empty, so that the cursor below the frame never scrolls the screen
+        let lines = frame(execution, &self.log, &self.view, columns as usize, rows as usize - 1);
+        for (y, line) in lines.iter().enumerate() {
+            if self.screen.get(y) != Some(line) {
+                self.term.move_cursor_to(0, y)?;
+                self.term.write_str(line)?;
+            }
+        }
+        self.term.move_cursor_to(0, lines.len())?;
+        self.term.flush()?;
+        self.screen = lines;
+        Ok(())
+    }
+}
+
+/// Remembers the last steps of an execution as lines of the step log, and which cells of the first
+/// tape the current test case touched.
+struct StepLog {
+    program: Program,
+    case: usize,
+    lines: VecDeque<String>,
+    /// First and last cell the head moved over or wrote to
+    touched: Option<(i64, i64)>,
+}
+
+impl StepLog {
+    fn new(program: Program) -> StepLog {
+        StepLog {
+            program,
+            case: 0,
+            lines: VecDeque::new(),
+            touched: None,
+        }
+    }
+
+    fn touch(&mut self, position: i64) {
+        self.touched = Some(match self.touched {
+            Some((first, last)) => (first.min(position), last.max(position)),
+            None => (position, position),
+        });
+    }
+
+    fn push(&mut self, line: String) {
+        if self.lines.len() == LOG_SIZE {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+}
+
+impl ExecutionObserver for StepLog {
+    fn on_step(&mut self, step: &Step) -> ControlFlow<()> {
+        let card = &self.program.cards[step.card];
+        let tapes = self.program.tapes();
+        let (read, _, instruction) = card.all_instructions().nth(step.instruction).unwrap();
+        let rule = describe_rule(&self.program, read, tapes, instruction);
+        self.push(format!("Case {}: {} {}", self.case, card.name, rule));
+        self.touch(step.from.x());
+        self.touch(step.to.x());
+        ControlFlow::Continue(())
+    }
+
+    fn on_write(&mut self, tape: usize, cell: Cell, _on: bool) {
+        if tape == 0 {
+            self.touch(cell.x());
+        }
+    }
+
+    fn on_case_complete(&mut self, state: &TestCaseExecutionState) {
+        let result = match state {
+            TestCaseExecutionState::Failure { errors } => format!("Failure: {}", errors.join(", ")),
+            _ => "Success".to_string(),
+        };
+        self.push(format!("Case {}: {}", self.case, result));
+        self.case += 1;
+        self.touched = None;
+    }
+}
+
+/// Every line of a frame of `width` columns and at most `height` rows. Each line is padded to the
+/// full width, so that drawing it covers whatever was there before.
+fn frame(execution: &LevelExecution, log: &StepLog, view: &TapeView, width: usize, height: usize) -> Vec<String> {
+    let tce = execution.current_execution();
+    let current_case = execution.executions.iter().position(|tce| !tce.is_terminated());
+    let mut lines = vec![fit(
+        &format!("Level: {}   Program: {}   Steps: {}", execution.level.name, execution.program.name, execution.get_steps()),
+        width,
+    )];
+
+    let tape = match tce {
+        Some(tce) => {
+            let mut tape = tape_lines(tce, view, width, log.touched);
+            let card = tce.get_current_card().map_or(String::new(), |card| format!("Current card: {}   ", card.name));
+            let touched = log.touched.map_or(String::new(), |(first, last)| format!("Touched: {} to {}", first, last));
+            tape.push(card + &touched);
+            tape
+        }
+        None => vec![format!("Total steps: {}", execution.get_steps())],
+    };
+
+    // The program and the test cases side by side get what the tape and the step log leave over
+    let program_rows = program_rows(&execution.program, tce);
+    let case_rows = case_rows(execution, current_case);
+    let wanted = 1 + program_rows.len().max(case_rows.len());
+    let left = height.saturating_sub(lines.len() + 1 + tape.len() + 1 + LOG_ROWS);
+    let top = wanted.min(left).max(2);
+    let left_width = (width.saturating_sub(measure_text_width(SEPARATOR)) * 3 / 5).max(1);
+    let right_width = width.saturating_sub(left_width + measure_text_width(SEPARATOR));
+    let program_pane = pane("Program", &program_rows, top, left_width);
+    let case_pane = pane("Test cases", &case_rows, top, right_width);
+    for (program_line, case_line) in program_pane.iter().zip(&case_pane) {
+        lines.push(fit(&format!("{}{}{}", program_line, SEPARATOR, case_line), width));
+    }
+
+    lines.push(fit(&style("Tape").bold().to_string(), width));
+    lines.extend(tape.iter().map(|line| fit(line, width)));
+
+    let log_rows = height.saturating_sub(lines.len() + 1).max(1);
+    lines.push(fit(&style("Steps").bold().to_string(), width));
+    let skip = log.lines.len().saturating_sub(log_rows);
+    lines.extend(log.lines.iter().skip(skip).map(|line| fit(line, width)));
+    while lines.len() < height {
+        lines.push(" ".repeat(width));
+    }
+    lines.truncate(height);
+    lines
+}
+
+/// A row of a pane, which is highlighted if it is the active one
+struct Row {
+    text: String,
+    active: bool,
+}
+
+/// A pane with a title and as many of its rows as fit in `height` lines, scrolled to the active row
+fn pane(title: &str, rows: &[Row], height: usize, width: usize) -> Vec<String> {
+    let visible = height.saturating_sub(1);
+    let active = rows.iter().position(|row| row.active).unwrap_or(0);
+    let start = active.saturating_sub(visible / 2).min(rows.len().saturating_sub(visible));
+    let mut lines = vec![fit(&style(title).bold().to_string(), width)];
+    for row in rows.iter().skip(start).take(visible) {
+        let line = fit(&row.text, width);
+        lines.push(if row.active { style(line).reverse().to_string() } else { line });
+    }
+    while lines.len() < height {
+        lines.push(" ".repeat(width));
+    }
+    lines
+}
+
+/// The card table of a program, with a row per instruction and the one the current card executes
+/// next highlighted
+fn program_rows(program: &Program, tce: Option<&TestCaseExecution>) -> Vec<Row> {
+    let tapes = program.tapes();
+    let current = tce.and_then(|tce| tce.get_current_card_index().map(|card| (card, tce.get_read())));
+    let width = program.cards.iter().map(|card| measure_text_width(&card.name)).max().unwrap_or(0);
+    let mut rows = Vec::new();
+    for (index, card) in program.cards.iter().enumerate() {
+        let is_current = current.is_some_and(|(card, _)| card == index);
+        let marker = if is_current { "▶" } else { " " };
+        let reads = (0..card.instructions().count()).map(|read| (read, card.instruction(read), false));
+        let alternatives = card.alternatives.iter().map(|alternative| (alternative.read, &alternative.instruction, true));
+        for (row, (read, instruction, alternative)) in reads.chain(alternatives).enumerate() {
+            let name = if row == 0 { card.name.as_str() } else { "" };
+            let or = if alternative { "or " } else { "" };
+            rows.push(Row {
+                text: format!("{} {} {}{}", marker, pad_str(name, width, Alignment::Left, None), or, describe_rule(program, read, tapes, instruction)),
+                active: !alternative && current == Some((index, read)),
+            });
+        }
+    }
+    rows
+}
+
+/// The test cases with their state and steps, with the running one highlighted
+fn case_rows(execution: &LevelExecution, current_case: Option<usize>) -> Vec<Row> {
+    execution.executions.iter().enumerate()
+        .map(|(index, tce)| {
+            let state = match tce.get_state() {
+                TestCaseExecutionState::Pending => "Pending".to_string(),
+                TestCaseExecutionState::Running => format!("Running {:>6}", tce.steps),
+                TestCaseExecutionState::Success => format!("Success {:>6}", tce.steps),
+                TestCaseExecutionState::Failure { errors } => format!("Failure {:>6} {}", tce.steps, errors.join(", ")),
+            };
+            Row {
+                text: format!("{:>4}: {}", index, state),
+                active: current_case == Some(index),
+            }
+        })
+        .collect()
+}
+
+/// An instruction as a rule of the `.tur` text format, such as `off -> write 1, R, Next`
+fn describe_rule(program: &Program, read: usize, tapes: usize, instruction: &Instruction) -> String {
+    let read = (0..tapes).map(|tape| if read >> tape & 1 == 1 { "on " } else { "off" }).collect::<Vec<_>>().join(" ");
+    let describe_action = |write_symbol: Option<bool>, move_direction: Option<Direction>| {
+        let write = write_symbol.map(|on| format!("write {}", on as u8));
+        let direction = move_direction.map(|direction| match direction {
+            Direction::Left => "L",
+            Direction::Right => "R",
+            Direction::Up => "U",
+            Direction::Down => "D",
+        }.to_string());
+        let items: Vec<String> = write.into_iter().chain(direction).collect();
+        if items.is_empty() { "-".to_string() } else { items.join(", ") }
+    };
+    let actions: Vec<String> = std::iter::once(describe_action(instruction.write_symbol, instruction.move_direction))
+        .chain(instruction.other_tapes.iter().map(|action| describe_action(action.write_symbol, action.move_direction)))
+        .collect();
+    let actions = if actions.iter().all(|action| action == "-") {
+        String::new()
+    } else {
+        format!("{}, ", actions.join(" | "))
+    };
+    let next = instruction.next_card.map_or("halt", |card| &program.cards[card].name);
+    format!("{} -> {}{}", read, actions, next)
+}
+
+/// A line cut or padded to exactly `width` columns
+fn fit(line: &str, width: usize) -> String {
+    pad_str(&truncate_str(line, width, ""), width, Alignment::Left, None).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use console::measure_text_width;
+
+    use crate::execution::LevelExecution;
+    use crate::{levels, programs};
+    use crate::render::TapeView;
+    use crate::tui::{frame, StepLog};
+
+    #[test]
+    fn frames_fill_the_screen_and_mark_the_current_card() {
+        let level = levels::builtins().into_iter().find(|level| level.name == "addition").unwrap();
+        let program = programs::builtins().into_iter().find(|program| program.name == "addition_solution").unwrap();
+        let mut execution = LevelExecution::new(level, program.clone());
+        let mut log = StepLog::new(program);
+        for _ in 0..3 {
+            assert!(execution.step_observed(&mut log).is_continue());
+        }
+
+        let lines = frame(&execution, &log, &TapeView::default(), 100, 30);
+        assert_eq!(lines.len(), 30);
+        assert!(lines.iter().all(|line| measure_text_width(line) == 100), "{:#?}", lines);
+        let card = execution.current_execution().unwrap().get_current_card().unwrap();
+        assert!(lines.iter().any(|line| line.starts_with(&format!("▶ {}", card.name))), "{:#?}", lines);
+        assert!(lines.iter().any(|line| line.starts_with("Case 0: ")), "{:#?}", lines);
+    }
+
+    #[test]
+    fn frames_fit_small_screens() {
+        let level = levels::builtins().into_iter().find(|level| level.name == "addition").unwrap();
+        let program = programs::builtins().into_iter().find(|program| program.name == "addition_solution").unwrap();
+        let execution = LevelExecution::new(level, program.clone());
+
+        let lines = frame(&execution, &StepLog::new(program), &TapeView::default(), 20, 8);
+        assert_eq!(lines.len(), 8);
+        assert!(lines.iter().all(|line| measure_text_width(line) == 20), "{:#?}", lines);
+    }
+}